@@ -0,0 +1,217 @@
+use std::{
+    fmt,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    str::FromStr,
+};
+
+/// Special-purpose category of an IP address, following the IANA
+/// special-purpose address registries.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IpCategory {
+    Global,
+    Unspecified,
+    Private,
+    SharedAddress,
+    Loopback,
+    LinkLocal,
+    UniqueLocal,
+    Documentation,
+    Benchmarking,
+    Multicast,
+    Broadcast,
+    Reserved,
+}
+
+impl IpCategory {
+    /// Ranges that are only reachable from inside a network, which make
+    /// sense to scan in internal assessments.
+    pub fn is_internal(&self) -> bool {
+        matches!(
+            self,
+            IpCategory::Private
+                | IpCategory::SharedAddress
+                | IpCategory::LinkLocal
+                | IpCategory::UniqueLocal
+        )
+    }
+
+    pub fn is_scannable(&self, include_internal: bool) -> bool {
+        *self == IpCategory::Global || (include_internal && self.is_internal())
+    }
+}
+
+impl fmt::Display for IpCategory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            IpCategory::Global => "global",
+            IpCategory::Unspecified => "unspecified",
+            IpCategory::Private => "private",
+            IpCategory::SharedAddress => "shared-address",
+            IpCategory::Loopback => "loopback",
+            IpCategory::LinkLocal => "link-local",
+            IpCategory::UniqueLocal => "unique-local",
+            IpCategory::Documentation => "documentation",
+            IpCategory::Benchmarking => "benchmarking",
+            IpCategory::Multicast => "multicast",
+            IpCategory::Broadcast => "broadcast",
+            IpCategory::Reserved => "reserved",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+lazy_static! {
+    // Order matters, the first matching range wins.
+    static ref SPECIAL_RANGES: Vec<(Cidr, IpCategory)> = vec![
+        ("255.255.255.255/32", IpCategory::Broadcast),
+        ("0.0.0.0/8", IpCategory::Unspecified),
+        ("10.0.0.0/8", IpCategory::Private),
+        ("172.16.0.0/12", IpCategory::Private),
+        ("192.168.0.0/16", IpCategory::Private),
+        ("100.64.0.0/10", IpCategory::SharedAddress),
+        ("127.0.0.0/8", IpCategory::Loopback),
+        ("169.254.0.0/16", IpCategory::LinkLocal),
+        ("192.0.0.0/24", IpCategory::Reserved),
+        ("192.0.2.0/24", IpCategory::Documentation),
+        ("198.51.100.0/24", IpCategory::Documentation),
+        ("203.0.113.0/24", IpCategory::Documentation),
+        ("198.18.0.0/15", IpCategory::Benchmarking),
+        ("224.0.0.0/4", IpCategory::Multicast),
+        ("240.0.0.0/4", IpCategory::Reserved),
+        ("::/128", IpCategory::Unspecified),
+        ("::1/128", IpCategory::Loopback),
+        ("100::/64", IpCategory::Reserved),
+        ("2001:2::/48", IpCategory::Benchmarking),
+        ("2001:db8::/32", IpCategory::Documentation),
+        ("fc00::/7", IpCategory::UniqueLocal),
+        ("fe80::/10", IpCategory::LinkLocal),
+        ("ff00::/8", IpCategory::Multicast),
+    ]
+    .into_iter()
+    .map(|(cidr, category)| (cidr.parse().unwrap(), category))
+    .collect();
+}
+
+pub fn classify_ip(ip: &IpAddr) -> IpCategory {
+    // IPv4-mapped IPv6 addresses get the category of the IPv4 address
+    if let IpAddr::V6(ipv6) = ip {
+        if let Some(ipv4) = ipv6.to_ipv4_mapped() {
+            return classify_ip(&IpAddr::V4(ipv4));
+        }
+    }
+    SPECIAL_RANGES
+        .iter()
+        .find(|(cidr, _)| cidr.contains(ip))
+        .map(|(_, category)| *category)
+        .unwrap_or(IpCategory::Global)
+}
+
+/// An IPv4 or IPv6 network in CIDR notation, e.g. `192.0.2.0/24`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    pub fn new(ip: IpAddr, prefix: u8) -> Option<Cidr> {
+        if prefix > max_prefix(&ip) {
+            return None;
+        }
+        let network = from_bits(&ip, to_bits(&ip) & mask(&ip, prefix));
+        Some(Cidr { network, prefix })
+    }
+
+    pub fn network(&self) -> IpAddr {
+        self.network
+    }
+
+    pub fn prefix(&self) -> u8 {
+        self.prefix
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        self.network.is_ipv4() == ip.is_ipv4()
+            && to_bits(ip) & mask(ip, self.prefix) == to_bits(&self.network)
+    }
+
+    /// Number of addresses returned by `hosts`.
+    pub fn size(&self) -> u128 {
+        let total = 1u128
+            .checked_shl(u32::from(max_prefix(&self.network) - self.prefix))
+            .unwrap_or(u128::MAX);
+        if self.network.is_ipv4() && self.prefix <= 30 {
+            total - 2
+        } else {
+            total
+        }
+    }
+
+    /// Addresses of the network. For IPv4 networks bigger than a /31 the
+    /// network and broadcast addresses are left out.
+    pub fn hosts(&self) -> impl Iterator<Item = IpAddr> {
+        let network = self.network;
+        let first = to_bits(&network);
+        let skip_edges = u128::from(network.is_ipv4() && self.prefix <= 30);
+        let start = first + skip_edges;
+        let end = (first | !mask(&network, self.prefix) & max_bits(&network)) - skip_edges;
+        (start..=end).map(move |bits| from_bits(&network, bits))
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Cidr, String> {
+        let (ip, prefix) = match s.split_once('/') {
+            Some(parts) => parts,
+            None => return Err(format!("{} is not in CIDR notation", s)),
+        };
+        let ip = ip.parse::<IpAddr>().map_err(|e| e.to_string())?;
+        let prefix = prefix.parse::<u8>().map_err(|e| e.to_string())?;
+        Cidr::new(ip, prefix).ok_or_else(|| format!("Invalid prefix length in {}", s))
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix)
+    }
+}
+
+fn max_prefix(ip: &IpAddr) -> u8 {
+    if ip.is_ipv4() {
+        32
+    } else {
+        128
+    }
+}
+
+fn max_bits(ip: &IpAddr) -> u128 {
+    if ip.is_ipv4() {
+        u128::from(u32::MAX)
+    } else {
+        u128::MAX
+    }
+}
+
+fn mask(ip: &IpAddr, prefix: u8) -> u128 {
+    let host_bits = u32::from(max_prefix(ip) - prefix);
+    max_bits(ip) & !(1u128.checked_shl(host_bits).unwrap_or(0).wrapping_sub(1))
+}
+
+fn to_bits(ip: &IpAddr) -> u128 {
+    match ip {
+        IpAddr::V4(ip) => u128::from(u32::from(*ip)),
+        IpAddr::V6(ip) => u128::from(*ip),
+    }
+}
+
+fn from_bits(family: &IpAddr, bits: u128) -> IpAddr {
+    if family.is_ipv4() {
+        IpAddr::V4(Ipv4Addr::from(bits as u32))
+    } else {
+        IpAddr::V6(Ipv6Addr::from(bits))
+    }
+}
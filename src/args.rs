@@ -1,23 +1,224 @@
 use {
     crate::{
         defaults,
+        errors::*,
+        files,
         logic::validate_target,
         misc::{return_matches_vec, sanitize_target_string},
-        structs::Args,
+        nameservers::Nameserver,
+        networking,
+        scanners::ScannerBackend,
+        scope::Scope,
+        structs::{Args, ScanConfig},
     },
     chrono::Utc,
-    clap::{load_yaml, value_t, App},
-    std::{collections::HashSet, time::Instant},
+    clap::{load_yaml, value_t, App, ArgMatches},
+    config::{Config, ConfigError, Value},
+    std::{
+        collections::{HashMap, HashSet},
+        path::Path,
+        time::{Duration, Instant},
+    },
+    yaml_rust::Yaml,
 };
 
+// Options that only make sense for a single run
+const CLI_ONLY_ARGS: &[&str] = &[
+    "target",
+    "files",
+    "stdin",
+    "resume",
+    "offline",
+    "hosts-map",
+    "config",
+    "profile",
+];
+
+// Options parsed as numbers, which clap leaves to value_t!
+const NUMERIC_ARGS: &[&str] = &[
+    "threads",
+    "batch-size",
+    "connect-timeout",
+    "max-cidr-hosts",
+    "max-resolver-latency",
+    "consensus",
+    "quorum",
+    "dns-timeout",
+    "dns-attempts",
+    "dns-concurrent-reqs",
+    "servfail-retries",
+];
+
+/// Command line arguments with the configuration file as fallback. Options
+/// given in the command line always win, and the options of the file that
+/// conflict with them are ignored. The options of the selected profile
+/// override the ones at the top of the file.
+struct Settings<'a> {
+    matches: ArgMatches<'a>,
+    file_values: HashMap<String, Vec<String>>,
+}
+
+impl<'a> Settings<'a> {
+    fn new(yaml: &Yaml, matches: ArgMatches<'a>) -> Result<Settings<'a>> {
+        let mut settings = Settings {
+            matches,
+            file_values: HashMap::new(),
+        };
+        let file = match settings.matches.value_of("config") {
+            Some(file) => file.to_string(),
+            None => match files::return_config_filename() {
+                Some(file) if Path::new(&file).is_file() => file,
+                file => match settings.matches.value_of("profile") {
+                    Some(profile) => {
+                        return Err(UnimapError::InvalidConfig {
+                            file: file.unwrap_or_default(),
+                            description: format!(
+                                "the file doesn't exist and the profile {} needs it",
+                                profile
+                            ),
+                        }
+                        .into())
+                    }
+                    None => return Ok(settings),
+                },
+            },
+        };
+        let invalid_config = |description: String| UnimapError::InvalidConfig {
+            file: file.clone(),
+            description,
+        };
+
+        let mut config = Config::new();
+        config
+            .merge(config::File::from(Path::new(&file)))
+            .map_err(|e| invalid_config(e.to_string()))?;
+        let profile = match settings.matches.value_of("profile") {
+            Some(profile) => Some(
+                config
+                    .get_table(&format!("profile.{}", profile))
+                    .map_err(|_| invalid_config(format!("there is no profile {}", profile)))?,
+            ),
+            None => None,
+        };
+
+        // The file uses the long names of the options
+        let mut longs = HashMap::new();
+        let mut conflicts: HashMap<&str, Vec<&str>> = HashMap::new();
+        let mut possible_values: HashMap<&str, Vec<&str>> = HashMap::new();
+        for arg in yaml["args"].as_vec().into_iter().flatten() {
+            for (name, options) in arg.as_hash().into_iter().flatten() {
+                let name = name.as_str().unwrap_or_default();
+                if let Some(long) = options["long"].as_str() {
+                    longs.insert(name, long);
+                }
+                if let Some(values) = options["possible_values"].as_vec() {
+                    possible_values.insert(
+                        name,
+                        values.iter().filter_map(|value| value.as_str()).collect(),
+                    );
+                }
+                for conflict in options["conflicts_with"]
+                    .as_vec()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|conflict| conflict.as_str())
+                {
+                    conflicts.entry(name).or_default().push(conflict);
+                    conflicts.entry(conflict).or_default().push(name);
+                }
+            }
+        }
+
+        for (name, long) in longs {
+            let overridden = settings.matches.is_present(name)
+                || conflicts
+                    .get(name)
+                    .into_iter()
+                    .flatten()
+                    .any(|conflict| settings.matches.is_present(conflict));
+            if overridden || CLI_ONLY_ARGS.contains(&name) {
+                continue;
+            }
+            let value = profile
+                .as_ref()
+                .and_then(|profile| profile.get(long).cloned())
+                .or_else(|| config.get::<Value>(long).ok());
+            if let Some(value) = value {
+                let values = return_config_values(value)
+                    .map_err(|e| invalid_config(format!("{}: {}", long, e)))?;
+                // Values of the file don't go through clap, so they are
+                // validated here the same way
+                for value in &values {
+                    let valid = match possible_values.get(name) {
+                        Some(possible_values) => possible_values.contains(&value.as_str()),
+                        None => !NUMERIC_ARGS.contains(&name) || value.parse::<u64>().is_ok(),
+                    };
+                    if !valid {
+                        return Err(invalid_config(format!(
+                            "{}: {} isn't a valid value",
+                            long, value
+                        ))
+                        .into());
+                    }
+                }
+                if !values.is_empty() {
+                    settings.file_values.insert(name.to_string(), values);
+                }
+            }
+        }
+        Ok(settings)
+    }
+
+    fn is_present(&self, name: &str) -> bool {
+        self.matches.is_present(name) || self.file_values.contains_key(name)
+    }
+
+    fn value_of(&self, name: &str) -> Option<&str> {
+        self.matches.value_of(name).or_else(|| {
+            self.file_values
+                .get(name)
+                .and_then(|values| values.first())
+                .map(String::as_str)
+        })
+    }
+
+    fn values_of(&self, name: &str) -> Vec<String> {
+        if self.matches.is_present(name) {
+            return_matches_vec(&self.matches, name)
+        } else {
+            self.file_values.get(name).cloned().unwrap_or_default()
+        }
+    }
+}
+
+// Flags set to false are left out as if they weren't in the file
+fn return_config_values(value: Value) -> std::result::Result<Vec<String>, ConfigError> {
+    if let Ok(flag) = value.clone().into_bool() {
+        if value.clone().into_str()?.parse::<bool>().is_ok() {
+            return Ok(if flag {
+                vec![String::new()]
+            } else {
+                Vec::new()
+            });
+        }
+    }
+    match value.clone().into_array() {
+        Ok(values) => values.into_iter().map(Value::into_str).collect(),
+        Err(_) => Ok(vec![value.into_str()?]),
+    }
+}
+
 #[allow(clippy::cognitive_complexity)]
-pub fn get_args() -> Args {
+pub fn get_args() -> Result<Args> {
     let yaml = load_yaml!("cli.yml");
-    let matches = App::from_yaml(yaml)
-        .version(clap::crate_version!())
-        .get_matches();
+    let matches = Settings::new(
+        yaml,
+        App::from_yaml(yaml)
+            .version(clap::crate_version!())
+            .get_matches(),
+    )?;
 
-    Args {
+    Ok(Args {
         target: {
             let target = sanitize_target_string(
                 value_t!(matches, "target", String).unwrap_or_else(|_| String::new()),
@@ -29,17 +230,26 @@ pub fn get_args() -> Args {
             }
         },
         file_name: if matches.is_present("output") {
-            value_t!(matches, "logs-dir", String).unwrap_or_else(|_| "unimap_logs".to_string())
+            value_t!(matches, "resume", String)
+                .or_else(|_| value_t!(matches, "logs-dir", String))
+                .unwrap_or_else(|_| "unimap_logs".to_string())
                 + "/"
                 + "unimap"
                 + &Utc::now().format("-log-%Y-%m-%d_%H-%M-%S").to_string()
-                + ".csv"
+                + if matches.is_present("json-output") {
+                    ".json"
+                } else if matches.is_present("jsonl-output") {
+                    ".jsonl"
+                } else {
+                    ".csv"
+                }
         } else if matches.is_present("unique-output") {
             matches.value_of("unique-output").unwrap().to_string()
         } else {
             String::new()
         },
-        logs_dir: value_t!(matches, "logs-dir", String)
+        logs_dir: value_t!(matches, "resume", String)
+            .or_else(|_| value_t!(matches, "logs-dir", String))
             .unwrap_or_else(|_| "unimap_logs".to_string()),
         threads: if matches.is_present("ports") && !matches.is_present("threads") {
             30
@@ -58,15 +268,129 @@ pub fn get_args() -> Args {
         no_keep_nmap_logs: matches.is_present("no-keep-nmap-logs"),
         raw_output: matches.is_present("raw-output"),
         url_output: matches.is_present("url-output"),
+        json_output: matches.is_present("json-output"),
+        jsonl_output: matches.is_present("jsonl-output"),
         from_stdin: matches.is_present("stdin"),
-        files: return_matches_vec(&matches, "files"),
+        scanner: value_t!(matches, "scanner", ScannerBackend).unwrap_or_default(),
+        batch_size: value_t!(matches, "batch-size", usize).unwrap_or_else(|_| 1),
+        connect_timeout: value_t!(matches, "connect-timeout", u64).unwrap_or_else(|_| 1000),
+        scripts: value_t!(matches, "script", String).unwrap_or_else(|_| String::new()),
+        script_args: value_t!(matches, "script-args", String).unwrap_or_else(|_| String::new()),
+        ipv6: matches.is_present("ipv6"),
+        resume: matches.is_present("resume"),
+        offline: matches.is_present("offline"),
+        hosts_map: value_t!(matches, "hosts-map", String).unwrap_or_else(|_| String::new()),
+        max_cidr_hosts: value_t!(matches, "max-cidr-hosts", usize).unwrap_or_else(|_| 65536),
+        files: matches.values_of("files"),
+        scope_files: matches.values_of("scope-file"),
+        exclude_files: matches.values_of("exclude-file"),
+        include_internal: matches.is_present("include-internal"),
+        drop_wildcards: matches.is_present("drop-wildcards"),
+        skip_resolver_check: matches.is_present("skip-resolver-check"),
+        max_resolver_latency: value_t!(matches, "max-resolver-latency", u64)
+            .unwrap_or_else(|_| 500),
+        consensus: value_t!(matches, "consensus", usize).unwrap_or_else(|_| 0),
+        quorum: value_t!(matches, "quorum", usize).unwrap_or_else(|_| 0),
+        dns_timeout: value_t!(matches, "dns-timeout", u64).unwrap_or_else(|_| 1000),
+        dns_attempts: value_t!(matches, "dns-attempts", usize).unwrap_or_else(|_| 2),
+        dns_concurrent_reqs: value_t!(matches, "dns-concurrent-reqs", usize).unwrap_or_else(|_| 1),
+        servfail_retries: value_t!(matches, "servfail-retries", usize).unwrap_or_else(|_| 0),
+        system_resolvers: matches.is_present("system-resolvers"),
         min_rate: value_t!(matches, "min-rate", String).unwrap_or_else(|_| String::new()),
         resolvers: if matches.is_present("custom-resolvers") {
-            return_matches_vec(&matches, "custom-resolvers")
+            matches.values_of("custom-resolvers")
         } else {
             defaults::ipv4_resolvers()
         },
         targets: HashSet::new(),
         time_wasted: Instant::now(),
+    })
+}
+
+pub fn return_scan_config(args: &Args) -> Result<ScanConfig> {
+    let resolvers = if args.custom_resolvers {
+        files::return_file_targets(args, args.resolvers.clone())?
+    } else if args.system_resolvers {
+        Vec::new()
+    } else {
+        args.resolvers.clone()
+    };
+    let mut builder = ScanConfig::builder();
+    let mut nameservers = Vec::new();
+    if args.system_resolvers {
+        let (system_nameservers, search_domains, ndots) = networking::return_system_config()?;
+        // IPv6 nameservers are skipped instead of failing, they can't be
+        // removed from the system configuration just for this run
+        nameservers.extend(
+            system_nameservers
+                .into_iter()
+                .filter(|nameserver| nameserver.socket_addr.is_ipv4() || args.ipv6),
+        );
+        if nameservers.is_empty() {
+            return Err(UnimapError::SystemResolvers {
+                description: "there are only IPv6 nameservers, they require the --ipv6 option"
+                    .to_string(),
+            }
+            .into());
+        }
+        builder = builder.search_domains(search_domains).ndots(ndots)
     }
+    for r in &resolvers {
+        match r.parse::<Nameserver>() {
+            Ok(nameserver) if nameserver.socket_addr.is_ipv4() || args.ipv6 => {
+                nameservers.push(nameserver)
+            }
+            Ok(_) => {
+                return Err(UnimapError::InvalidResolver {
+                    resolver: r.to_string(),
+                    description: "IPv6 nameservers require the --ipv6 option".to_string(),
+                }
+                .into())
+            }
+            Err(e) => {
+                return Err(UnimapError::InvalidResolver {
+                    resolver: r.to_string(),
+                    description: e,
+                }
+                .into())
+            }
+        }
+    }
+
+    if !args.hosts_map.is_empty() {
+        builder = builder.hosts_map(&args.hosts_map)
+    }
+
+    Ok(builder
+        .resolvers(nameservers)
+        .threads(args.threads)
+        .logs_dir(&args.logs_dir)
+        .ports(&args.ports)
+        .min_rate(&args.min_rate)
+        .fast_scan(args.fast_scan)
+        .no_keep_nmap_logs(args.no_keep_nmap_logs)
+        .scanner(args.scanner)
+        .batch_size(args.batch_size)
+        .connect_timeout(Duration::from_millis(args.connect_timeout))
+        .scripts(&args.scripts)
+        .script_args(&args.script_args)
+        .ipv6(args.ipv6)
+        .resume(args.resume)
+        .offline(args.offline)
+        .max_cidr_hosts(args.max_cidr_hosts)
+        .scope(Scope::from_files(&args.scope_files, &args.exclude_files)?)
+        .include_internal(args.include_internal)
+        .drop_wildcards(args.drop_wildcards)
+        // The system resolvers may only know internal names, so there is
+        // nothing reliable to check them against
+        .check_resolvers(!args.skip_resolver_check && !args.system_resolvers)
+        .max_resolver_latency(Duration::from_millis(args.max_resolver_latency))
+        .consensus(args.consensus)
+        .quorum(args.quorum)
+        .dns_timeout(Duration::from_millis(args.dns_timeout))
+        .dns_attempts(args.dns_attempts)
+        .dns_concurrent_reqs(args.dns_concurrent_reqs)
+        .servfail_retries(args.servfail_retries)
+        .quiet(args.quiet_flag)
+        .build())
 }
@@ -1,3 +1,7 @@
+// Name that every healthy resolver must answer, random labels under it are
+// used to check that non-existent names get a NXDOMAIN answer
+pub const HEALTH_CHECK_DOMAIN: &str = "google.com.";
+
 pub fn ipv4_resolvers() -> Vec<String> {
     // DNS were validate with dnsvalidator the 03/09/2020
     vec![
@@ -27,3 +31,19 @@ pub fn ipv4_resolvers() -> Vec<String> {
     .map(str::to_owned)
     .collect()
 }
+
+// Maximum number of connections open at the same time by the TCP connect
+// scanner, shared between the threads to stay below the usual 1024 open files
+// limit
+pub const CONNECT_MAX_SOCKETS: usize = 512;
+
+// The 100 most common TCP ports according to nmap-services, scanned by the TCP
+// connect scanner when no ports are given
+pub const TOP_TCP_PORTS: &[u16] = &[
+    7, 9, 13, 21, 22, 23, 25, 26, 37, 53, 79, 80, 81, 88, 106, 110, 111, 113, 119, 135, 139, 143,
+    144, 179, 199, 389, 427, 443, 444, 445, 465, 513, 514, 515, 543, 544, 548, 554, 587, 631, 646,
+    873, 990, 993, 995, 1025, 1026, 1027, 1028, 1029, 1110, 1433, 1720, 1723, 1755, 1900, 2000,
+    2001, 2049, 2121, 2717, 3000, 3128, 3306, 3389, 3986, 4899, 5000, 5009, 5051, 5060, 5101, 5190,
+    5357, 5432, 5631, 5666, 5800, 5900, 6000, 6001, 6646, 7070, 8000, 8008, 8009, 8080, 8081, 8443,
+    8888, 9100, 9999, 10000, 32768, 49152, 49153, 49154, 49155, 49156, 49157,
+];
@@ -1,2 +1,170 @@
 pub use failure::{Error, ResultExt};
+use {failure::Fail, std::fmt};
 pub type Result<T> = ::std::result::Result<T, Error>;
+
+#[derive(Debug, Clone)]
+pub enum UnimapError {
+    EmptyTargets,
+    FileOpen {
+        file: String,
+        description: String,
+    },
+    InvalidStateFile {
+        file: String,
+        description: String,
+    },
+    InvalidResolver {
+        resolver: String,
+        description: String,
+    },
+    NoScannableIps {
+        ips: Vec<String>,
+    },
+    NoHealthyResolvers,
+    SystemResolvers {
+        description: String,
+    },
+    InvalidConfig {
+        file: String,
+        description: String,
+    },
+    NmapNotFound,
+    ScannerNotFound {
+        scanner: String,
+    },
+    InsufficientPrivileges,
+    NmapFailed {
+        ip: String,
+        description: String,
+    },
+    XmlParse {
+        ip: String,
+        description: String,
+    },
+    XmlNotFound {
+        ip: String,
+        file: String,
+    },
+    ScannerFailed {
+        scanner: String,
+        ip: String,
+        description: String,
+    },
+    ScannerOutputParse {
+        scanner: String,
+        ip: String,
+        description: String,
+    },
+    ScannerOutputNotFound {
+        scanner: String,
+        ip: String,
+        file: String,
+    },
+    InvalidPorts {
+        ports: String,
+        description: String,
+    },
+    OutOfScope {
+        ip: String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for UnimapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UnimapError::EmptyTargets => write!(f, "Target is empty or invalid!"),
+            UnimapError::FileOpen { file, description } => {
+                write!(f, "Can not open file {}. Error: {}", file, description)
+            }
+            UnimapError::InvalidStateFile { file, description } => write!(
+                f,
+                "Can not load the scan state from {}. Error: {}",
+                file, description
+            ),
+            UnimapError::InvalidResolver {
+                resolver,
+                description,
+            } => write!(
+                f,
+                "Error parsing the resolver {}. Please check and try again. Error: {}",
+                resolver, description
+            ),
+            UnimapError::NoScannableIps { ips } => {
+                write!(f, "No valid IPs found for scanning. IPs found: {:?}", ips)
+            }
+            UnimapError::NoHealthyResolvers => write!(
+                f,
+                "None of the resolvers passed the health check, check them or use --skip-resolver-check."
+            ),
+            UnimapError::SystemResolvers { description } => write!(
+                f,
+                "Can not load the system resolvers configuration. Error: {}",
+                description
+            ),
+            UnimapError::InvalidConfig { file, description } => write!(
+                f,
+                "Error loading the configuration file {}. Error: {}",
+                file, description
+            ),
+            UnimapError::NmapNotFound => {
+                write!(f, "Nmap is not installed or it's not in the PATH.")
+            }
+            UnimapError::ScannerNotFound { scanner } => {
+                write!(f, "{} is not installed or it's not in the PATH.", scanner)
+            }
+            UnimapError::InsufficientPrivileges => write!(
+                f,
+                "The port scanner requires root/administrator permissions to run the scan."
+            ),
+            UnimapError::NmapFailed { ip, description } => write!(
+                f,
+                "Error running nmap against {}. Description: {}",
+                ip, description
+            ),
+            UnimapError::XmlParse { ip, description } => write!(
+                f,
+                "Error parsing the nmap XML data for {}. Description: {}",
+                ip, description
+            ),
+            UnimapError::XmlNotFound { ip, file } => write!(
+                f,
+                "There is no nmap XML data for {}, the file {} doesn't exist.",
+                ip, file
+            ),
+            UnimapError::ScannerFailed {
+                scanner,
+                ip,
+                description,
+            } => write!(
+                f,
+                "Error running {} against {}. Description: {}",
+                scanner, ip, description
+            ),
+            UnimapError::ScannerOutputParse {
+                scanner,
+                ip,
+                description,
+            } => write!(
+                f,
+                "Error parsing the {} output for {}. Description: {}",
+                scanner, ip, description
+            ),
+            UnimapError::ScannerOutputNotFound { scanner, ip, file } => write!(
+                f,
+                "There is no {} output for {}, the file {} doesn't exist.",
+                scanner, ip, file
+            ),
+            UnimapError::InvalidPorts { ports, description } => write!(
+                f,
+                "Error parsing the ports {}. Error: {}",
+                ports, description
+            ),
+            UnimapError::OutOfScope { ip, reason } => {
+                write!(f, "{} is out of scope, {}.", ip, reason)
+            }
+        }
+    }
+}
+
+impl Fail for UnimapError {}
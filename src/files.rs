@@ -1,30 +1,41 @@
 use {
-    crate::{errors::*, structs::Args},
+    crate::{
+        errors::*,
+        structs::{Args, ScanState},
+    },
     log::error,
     prettytable::Table,
     std::{
-        collections::HashSet,
+        collections::{BTreeMap, HashSet},
+        env,
         fs::{self, File, OpenOptions},
         io::{BufRead, BufReader, Write},
-        path::Path,
+        net::IpAddr,
+        path::{Path, PathBuf},
     },
 };
 
-pub fn return_file_targets(args: &Args, mut files: Vec<String>) -> Vec<String> {
+pub fn return_file_targets(args: &Args, mut files: Vec<String>) -> Result<Vec<String>> {
     let mut targets: Vec<String> = Vec::new();
     files.sort();
     files.dedup();
     for f in files {
         match File::open(&f) {
             Ok(file) => {
-                for target in BufReader::new(file).lines().flatten() {
+                for target in BufReader::new(file)
+                    .lines()
+                    .map_while(std::result::Result::ok)
+                {
                     targets.push(target);
                 }
             }
             Err(e) => {
                 if args.files.len() == 1 {
-                    error!("Can not open file {}. Error: {}\n", f, e);
-                    std::process::exit(1)
+                    return Err(UnimapError::FileOpen {
+                        file: f,
+                        description: e.to_string(),
+                    }
+                    .into());
                 } else if !args.quiet_flag {
                     error!(
                         "Can not open file {}, working with next file. Error: {}\n",
@@ -36,27 +47,127 @@ pub fn return_file_targets(args: &Args, mut files: Vec<String>) -> Vec<String> {
     }
     targets.sort();
     targets.dedup();
-    targets.iter().map(|target| target.to_lowercase()).collect()
+    Ok(targets.iter().map(|target| target.to_lowercase()).collect())
 }
 
-pub fn table_to_file(table: &Table, file_name: Option<std::fs::File>) -> Result<()> {
-    table.to_csv(file_name.unwrap())?;
+pub fn table_to_file(table: &Table, file: File) -> Result<()> {
+    table.to_csv(file)?;
     Ok(())
 }
 
-pub fn return_output_file(args: &Args) -> Option<File> {
+pub fn return_output_file(args: &Args) -> Result<Option<File>> {
     if args.file_name.is_empty() || !args.with_output {
-        None
+        Ok(None)
     } else {
-        Some(
-            OpenOptions::new()
-                .append(true)
+        let mut options = OpenOptions::new();
+        // A JSON document can't be appended to a previous one
+        if args.json_output {
+            options.write(true).truncate(true);
+        } else {
+            options.append(true);
+        }
+        Ok(Some(
+            options
                 .create(true)
                 .open(&args.file_name)
-                .with_context(|_| format!("Can't create file {}", &args.file_name))
-                .unwrap(),
+                .with_context(|_| format!("Can't create file {}", &args.file_name))?,
+        ))
+    }
+}
+
+pub fn return_scan_filename(logs_dir: &str, ip: &str, extension: &str) -> String {
+    // Colons from IPv6 addresses aren't valid in Windows filenames
+    format!("{}/{}.{}", logs_dir, ip.replace(':', "_"), extension)
+}
+
+pub fn return_hosts_map(file: &str) -> Result<BTreeMap<String, Vec<String>>> {
+    let data = fs::read_to_string(file).map_err(|e| UnimapError::FileOpen {
+        file: file.to_string(),
+        description: e.to_string(),
+    })?;
+    let mut hosts_map: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for line in data.lines() {
+        let mut fields = line
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|field| !field.is_empty());
+        if let Some(host) = fields.next() {
+            let ips = hosts_map.entry(host.to_lowercase()).or_default();
+            ips.extend(fields.map(str::to_owned));
+            ips.sort();
+            ips.dedup();
+        }
+    }
+    Ok(hosts_map)
+}
+
+// $XDG_CONFIG_HOME/unimap/config.toml, or ~/.config/unimap/config.toml
+pub fn return_config_filename() -> Option<String> {
+    let config_dir = env::var("XDG_CONFIG_HOME")
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            env::var("HOME")
+                .or_else(|_| env::var("USERPROFILE"))
+                .ok()
+                .map(|home| Path::new(&home).join(".config"))
+        })?;
+    Some(
+        config_dir
+            .join("unimap")
+            .join("config.toml")
+            .to_string_lossy()
+            .to_string(),
+    )
+}
+
+pub fn return_state_filename(logs_dir: &str) -> String {
+    format!("{}/unimap_state.json", logs_dir)
+}
+
+pub fn return_done_ips_filename(logs_dir: &str) -> String {
+    format!("{}/unimap_done_ips.txt", logs_dir)
+}
+
+pub fn return_scan_state(logs_dir: &str) -> Result<ScanState> {
+    let file = return_state_filename(logs_dir);
+    let data = fs::read_to_string(&file).map_err(|e| UnimapError::InvalidStateFile {
+        file: file.clone(),
+        description: e.to_string(),
+    })?;
+    let mut state: ScanState =
+        serde_json::from_str(&data).map_err(|e| UnimapError::InvalidStateFile {
+            file,
+            description: e.to_string(),
+        })?;
+    // IPs finished after the state was saved. The last line may be cut if
+    // the run was interrupted while writing it.
+    if let Ok(done_ips) = fs::read_to_string(return_done_ips_filename(logs_dir)) {
+        state.done_ips.extend(
+            done_ips
+                .lines()
+                .filter(|ip| ip.parse::<IpAddr>().is_ok())
+                .map(str::to_string),
         )
     }
+    Ok(state)
+}
+
+pub fn save_scan_state(logs_dir: &str, state: &ScanState) -> Result<()> {
+    // Write to a temporary file first so an interruption never leaves a
+    // truncated state file behind
+    let file = return_state_filename(logs_dir);
+    let tmp_file = format!("{}.tmp", file);
+    fs::write(&tmp_file, serde_json::to_string(state)?)?;
+    fs::rename(&tmp_file, &file)?;
+    Ok(())
+}
+
+/// Creates the log where the IPs are appended as their scans finish, the
+/// state file is only saved once per run. The IPs of a previous run must
+/// already be in the saved state, as the log is emptied.
+pub fn return_done_ips_file(logs_dir: &str) -> Result<File> {
+    Ok(File::create(return_done_ips_filename(logs_dir))?)
 }
 
 pub fn check_full_path(full_path: &str) -> bool {
@@ -7,15 +7,23 @@ extern crate prettytable;
 
 extern crate log;
 
+pub mod addresses;
 pub mod args;
 pub mod errors;
 pub mod files;
 pub mod logger;
+pub mod masscan;
 pub mod misc;
+pub mod naabu;
+pub mod nameservers;
+pub mod nmap;
+pub mod output;
 pub mod resolver_engine;
+pub mod scanners;
+pub mod scope;
+pub mod structs;
+pub mod tcp_connect;
 
 mod defaults;
 mod logic;
 mod networking;
-mod nmap;
-mod structs;
@@ -1,3 +1,5 @@
+use {crate::addresses::Cidr, std::net::IpAddr};
+
 lazy_static! {
     static ref SPECIAL_CHARS: Vec<char> = vec![
         '[', ']', '{', '}', '(', ')', '*', '|', ':', '<', '>', '/', '\\', '%', '&', '¿', '?', '¡',
@@ -6,17 +8,28 @@ lazy_static! {
 }
 
 pub fn validate_target(target: &str) -> bool {
+    if target.parse::<IpAddr>().is_ok() || target.parse::<Cidr>().is_ok() {
+        return true;
+    }
     !target.starts_with('.')
         && target.contains('.')
         && !target.contains(&SPECIAL_CHARS[..])
         && target.is_ascii()
 }
 
-pub fn null_ip_checker(ip: &str) -> String {
-    if ip.is_empty() {
+/// Domain one level above `target`, top-level domains aren't returned.
+pub fn return_parent_domain(target: &str) -> Option<&str> {
+    target
+        .split_once('.')
+        .map(|(_, parent)| parent)
+        .filter(|parent| parent.contains('.'))
+}
+
+pub fn return_ips_string(ips: &[String]) -> String {
+    if ips.is_empty() {
         String::from("NULL")
     } else {
-        ip.to_string()
+        ips.join(";")
     }
 }
 
@@ -1,39 +1,69 @@
 use {
     log::{error, Level},
     std::{collections::HashSet, iter::FromIterator},
-    unimap::{args, errors::*, files::return_file_targets, logger, misc, resolver_engine},
+    unimap::{
+        addresses::Cidr, args, errors::*, files::return_file_targets, logger, misc, resolver_engine,
+    },
 };
 
+// Exit codes follow sysexits.h where there is a matching one.
+fn exit_code(err: &Error) -> i32 {
+    match err.downcast_ref::<UnimapError>() {
+        Some(UnimapError::EmptyTargets) | Some(UnimapError::InvalidPorts { .. }) => 64,
+        Some(UnimapError::NoScannableIps { .. })
+        | Some(UnimapError::XmlParse { .. })
+        | Some(UnimapError::ScannerOutputParse { .. })
+        | Some(UnimapError::InvalidStateFile { .. }) => 65,
+        Some(UnimapError::FileOpen { .. })
+        | Some(UnimapError::XmlNotFound { .. })
+        | Some(UnimapError::ScannerOutputNotFound { .. }) => 66,
+        Some(UnimapError::NmapNotFound)
+        | Some(UnimapError::NmapFailed { .. })
+        | Some(UnimapError::ScannerNotFound { .. })
+        | Some(UnimapError::ScannerFailed { .. })
+        | Some(UnimapError::NoHealthyResolvers) => 69,
+        Some(UnimapError::InsufficientPrivileges) => 77,
+        Some(UnimapError::InvalidResolver { .. })
+        | Some(UnimapError::SystemResolvers { .. })
+        | Some(UnimapError::InvalidConfig { .. }) => 78,
+        Some(UnimapError::OutOfScope { .. }) => 1,
+        None => 1,
+    }
+}
+
 fn run() -> Result<()> {
     if std::env::var("UNIMAP_LOG_LEVEL").is_ok() {
         logger::init_by_env()
     } else {
         logger::init_with_level(Level::Info).unwrap()
     }
-    let mut arguments = args::get_args();
+    let mut arguments = args::get_args()?;
     if !arguments.files.is_empty() {
         arguments.targets =
-            HashSet::from_iter(return_file_targets(&arguments, arguments.files.clone()))
+            HashSet::from_iter(return_file_targets(&arguments, arguments.files.clone())?)
     } else if !arguments.target.is_empty() {
         arguments.targets.insert(arguments.target.clone());
-    } else {
+    } else if !arguments.resume && arguments.hosts_map.is_empty() {
         arguments.targets = misc::read_stdin()
     }
 
-    if arguments.targets.len() < 50 {
-        arguments.threads = arguments.targets.len()
-    }
+    let preresolved = arguments.resume || !arguments.hosts_map.is_empty();
 
-    rayon::ThreadPoolBuilder::new()
-        .num_threads(arguments.threads)
-        .build_global()
-        .unwrap();
+    // CIDR ranges are counted by the number of IPs they expand to
+    let targets_count = arguments
+        .targets
+        .iter()
+        .map(|target| target.parse::<Cidr>().map_or(1, |cidr| cidr.size()))
+        .fold(0u128, u128::saturating_add);
+    if targets_count < 50 && !preresolved {
+        arguments.threads = targets_count as usize
+    }
 
-    if !arguments.targets.is_empty() {
-        resolver_engine::parallel_resolver_all(&mut arguments)
+    if !arguments.targets.is_empty() || preresolved {
+        let config = args::return_scan_config(&arguments)?;
+        resolver_engine::parallel_resolver_all(&arguments, &config)
     } else {
-        error!("Error: Target is empty or invalid!\n");
-        std::process::exit(1)
+        Err(UnimapError::EmptyTargets.into())
     }
 }
 
@@ -43,6 +73,6 @@ fn main() {
         for cause in err.iter_chain().skip(1) {
             error!("Error description: {}\n", cause);
         }
-        std::process::exit(1);
+        std::process::exit(exit_code(&err));
     }
 }
@@ -0,0 +1,120 @@
+use {
+    crate::{
+        errors::*,
+        nmap::{Port, State},
+        scanners::{
+            read_scanner_file, return_batch_label, return_process_rate, run_scanner,
+            split_batch_output, write_batch_list,
+        },
+        structs::ScanConfig,
+    },
+    log::error,
+    std::{collections::BTreeMap, fs, net::IpAddr},
+};
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MasscanRecord {
+    pub ip: String,
+    #[serde(default)]
+    pub ports: Vec<MasscanPort>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MasscanPort {
+    pub port: u16,
+    pub proto: String,
+    pub status: String,
+    #[serde(default)]
+    pub reason: String,
+    pub ttl: Option<u32>,
+}
+
+pub fn get_masscan_data(filename: &str, host: &str, config: &ScanConfig) -> Result<Vec<Port>> {
+    let mut masscan_args = vec![host.to_string()];
+    masscan_args.append(&mut return_masscan_args(filename, config));
+
+    run_scanner("masscan", &masscan_args, filename, host)?;
+    parse_masscan_file(filename, host)
+}
+
+/// Scans several IPs with a single masscan process, passing them with -iL.
+/// The output of the whole batch is saved in the file of every IP.
+pub fn get_masscan_batch_data(batch: &[(String, String)], config: &ScanConfig) -> Result<()> {
+    let label = return_batch_label(batch);
+    let (list_filename, output_filename) = write_batch_list("masscan", batch, config)?;
+    let mut masscan_args = vec!["-iL".to_string(), list_filename.clone()];
+    masscan_args.append(&mut return_masscan_args(&output_filename, config));
+
+    let result = run_scanner("masscan", &masscan_args, &output_filename, &label);
+    if fs::remove_file(&list_filename).is_err() {
+        error!("Error removing filename {}.", &list_filename)
+    }
+    result?;
+    split_batch_output("masscan", batch, &output_filename)
+}
+
+fn return_masscan_args(filename: &str, config: &ScanConfig) -> Vec<String> {
+    let ports = config.ports.replace(' ', "");
+    // masscan waits 10 seconds for late responses by default, too much when
+    // it runs once per IP or batch
+    let mut masscan_args = vec!["-oJ", filename, "--wait", "3"];
+
+    if ports.is_empty() {
+        masscan_args.append(&mut vec!["--top-ports", "1000"])
+    } else {
+        masscan_args.append(&mut vec!["-p", &ports])
+    }
+
+    let rate = return_process_rate(config);
+    if !rate.is_empty() {
+        masscan_args.append(&mut vec!["--rate", &rate])
+    }
+
+    masscan_args.into_iter().map(str::to_owned).collect()
+}
+
+pub fn parse_masscan_file(filename: &str, host: &str) -> Result<Vec<Port>> {
+    let data = read_scanner_file("masscan", filename, host)?;
+    // Depending on the version, masscan writes a JSON array with one record
+    // per line or a list of records that isn't valid JSON as a whole, so the
+    // records are parsed line by line.
+    let mut ports = BTreeMap::new();
+    for line in data.lines() {
+        let line = line.trim().trim_matches(',');
+        if !line.starts_with('{') || line.starts_with("{finished") {
+            continue;
+        }
+        let record: MasscanRecord =
+            serde_json::from_str(line).map_err(|e| UnimapError::ScannerOutputParse {
+                scanner: "masscan".to_string(),
+                ip: host.to_string(),
+                description: e.to_string(),
+            })?;
+        // Batch outputs have the records of several IPs
+        if record.ip.parse::<IpAddr>().ok() != host.parse::<IpAddr>().ok() {
+            continue;
+        }
+        for port in record
+            .ports
+            .into_iter()
+            .filter(|port| port.status == "open")
+        {
+            ports.insert(
+                (port.proto.clone(), port.port),
+                Port {
+                    protocol: port.proto,
+                    portid: port.port.to_string(),
+                    state: State {
+                        state: port.status,
+                        reason: port.reason,
+                        reason_ttl: port.ttl.map(|ttl| ttl.to_string()).unwrap_or_default(),
+                        ..Default::default()
+                    },
+                    service: None,
+                    ..Default::default()
+                },
+            );
+        }
+    }
+    Ok(ports.into_values().collect())
+}
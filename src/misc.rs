@@ -1,9 +1,16 @@
-use std::{
-    collections::HashSet,
-    io::{self, Read},
+use {
+    crate::addresses::Cidr,
+    std::{
+        collections::HashSet,
+        io::{self, Read},
+        net::IpAddr,
+    },
 };
 
 pub fn sanitize_target_string(target: String) -> String {
+    if target.parse::<IpAddr>().is_ok() || target.parse::<Cidr>().is_ok() {
+        return target;
+    }
     target
         .replace("www.", "")
         .replace("https://", "")
@@ -0,0 +1,135 @@
+use {
+    crate::{
+        errors::*,
+        nmap::{Port, State},
+        scanners::{
+            read_scanner_file, return_batch_label, return_process_rate, run_scanner,
+            split_batch_output, write_batch_list,
+        },
+        structs::ScanConfig,
+    },
+    log::error,
+    std::{
+        collections::BTreeSet,
+        fs,
+        net::{IpAddr, Ipv6Addr},
+    },
+};
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NaabuRecord {
+    pub host: Option<String>,
+    pub ip: Option<String>,
+    pub port: NaabuPort,
+    pub protocol: Option<String>,
+}
+
+/// Old naabu versions write the port as an object.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum NaabuPort {
+    Number(u16),
+    Object {
+        #[serde(rename = "Port")]
+        port: u16,
+    },
+}
+
+impl Default for NaabuPort {
+    fn default() -> NaabuPort {
+        NaabuPort::Number(0)
+    }
+}
+
+impl NaabuPort {
+    pub fn number(&self) -> u16 {
+        match self {
+            NaabuPort::Number(port) | NaabuPort::Object { port } => *port,
+        }
+    }
+}
+
+pub fn get_naabu_data(filename: &str, host: &str, config: &ScanConfig) -> Result<Vec<Port>> {
+    let mut naabu_args = vec!["-host".to_string(), host.to_string()];
+    naabu_args.append(&mut return_naabu_args(filename, host, config));
+
+    run_scanner("naabu", &naabu_args, filename, host)?;
+    parse_naabu_file(filename, host)
+}
+
+/// Scans several IPs with a single naabu process, passing them with -list.
+/// The output of the whole batch is saved in the file of every IP.
+pub fn get_naabu_batch_data(batch: &[(String, String)], config: &ScanConfig) -> Result<()> {
+    let label = return_batch_label(batch);
+    let first_ip = batch.first().map(|(ip, _)| ip.as_str()).unwrap_or_default();
+    let (list_filename, output_filename) = write_batch_list("naabu", batch, config)?;
+    let mut naabu_args = vec!["-list".to_string(), list_filename.clone()];
+    naabu_args.append(&mut return_naabu_args(&output_filename, first_ip, config));
+
+    let result = run_scanner("naabu", &naabu_args, &output_filename, &label);
+    if fs::remove_file(&list_filename).is_err() {
+        error!("Error removing filename {}.", &list_filename)
+    }
+    result?;
+    split_batch_output("naabu", batch, &output_filename)
+}
+
+// IPv4 and IPv6 addresses are never mixed in a batch, so `host` tells the IP
+// version of all of them
+fn return_naabu_args(filename: &str, host: &str, config: &ScanConfig) -> Vec<String> {
+    let ports = config.ports.replace(' ', "");
+    let mut naabu_args = vec!["-json", "-silent", "-o", filename];
+
+    if ports.is_empty() {
+        naabu_args.append(&mut vec!["-top-ports", "1000"])
+    } else {
+        naabu_args.append(&mut vec!["-p", &ports])
+    }
+
+    let rate = return_process_rate(config);
+    if !rate.is_empty() {
+        naabu_args.append(&mut vec!["-rate", &rate])
+    }
+
+    if host.parse::<Ipv6Addr>().is_ok() {
+        naabu_args.append(&mut vec!["-ip-version", "6"])
+    }
+
+    naabu_args.into_iter().map(str::to_owned).collect()
+}
+
+pub fn parse_naabu_file(filename: &str, host: &str) -> Result<Vec<Port>> {
+    let data = read_scanner_file("naabu", filename, host)?;
+    let mut ports = BTreeSet::new();
+    for line in data.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let record: NaabuRecord =
+            serde_json::from_str(line).map_err(|e| UnimapError::ScannerOutputParse {
+                scanner: "naabu".to_string(),
+                ip: host.to_string(),
+                description: e.to_string(),
+            })?;
+        // Batch outputs have the records of several IPs
+        let record_ip = record.ip.as_ref().or(record.host.as_ref());
+        if record_ip.and_then(|ip| ip.parse::<IpAddr>().ok()) != host.parse::<IpAddr>().ok() {
+            continue;
+        }
+        ports.insert((
+            record.protocol.unwrap_or_else(|| "tcp".to_string()),
+            record.port.number(),
+        ));
+    }
+    // naabu only reports open ports, without the reason
+    Ok(ports
+        .into_iter()
+        .map(|(protocol, port)| Port {
+            protocol,
+            portid: port.to_string(),
+            state: State {
+                state: "open".to_string(),
+                ..Default::default()
+            },
+            service: None,
+            ..Default::default()
+        })
+        .collect())
+}
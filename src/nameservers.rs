@@ -0,0 +1,170 @@
+use std::{
+    fmt,
+    net::{IpAddr, SocketAddr, ToSocketAddrs},
+    str::FromStr,
+};
+
+/// Protocol used to talk to a nameserver.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum NameserverProtocol {
+    /// Classic DNS, over UDP with fallback to TCP.
+    Plain,
+    /// DNS-over-TLS.
+    Tls,
+    /// DNS-over-HTTPS.
+    Https,
+}
+
+/// A resolver entry: a plain IP address like `1.1.1.1`, a DNS-over-TLS entry
+/// like `tls://1.1.1.1#cloudflare-dns.com` or a DNS-over-HTTPS entry like
+/// `https://dns.google/dns-query`. Hostnames are resolved with the system
+/// resolver when parsing and used as the TLS name if none is given after `#`.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Nameserver {
+    pub socket_addr: SocketAddr,
+    pub protocol: NameserverProtocol,
+    pub tls_dns_name: Option<String>,
+}
+
+impl Nameserver {
+    pub fn is_plain(&self) -> bool {
+        self.protocol == NameserverProtocol::Plain
+    }
+}
+
+impl From<SocketAddr> for Nameserver {
+    fn from(socket_addr: SocketAddr) -> Nameserver {
+        Nameserver {
+            socket_addr,
+            protocol: NameserverProtocol::Plain,
+            tls_dns_name: None,
+        }
+    }
+}
+
+impl From<IpAddr> for Nameserver {
+    fn from(ip: IpAddr) -> Nameserver {
+        Nameserver::from(SocketAddr::new(ip, 53))
+    }
+}
+
+impl FromStr for Nameserver {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Nameserver, String> {
+        let (protocol, address) = match s.split_once("://") {
+            None => (NameserverProtocol::Plain, s),
+            Some(("tls", address)) if cfg!(feature = "dns-over-tls") => {
+                (NameserverProtocol::Tls, address)
+            }
+            Some(("https", address)) if cfg!(feature = "dns-over-https") => {
+                (NameserverProtocol::Https, address)
+            }
+            Some(("tls", _)) => return Err("unimap was built without DNS-over-TLS support".into()),
+            Some(("https", _)) => {
+                return Err("unimap was built without DNS-over-HTTPS support".into())
+            }
+            Some((scheme, _)) => return Err(format!("Unknown resolver protocol {}", scheme)),
+        };
+
+        if protocol == NameserverProtocol::Plain {
+            return s
+                .parse::<IpAddr>()
+                .map(Nameserver::from)
+                .or_else(|_| s.parse::<SocketAddr>().map(Nameserver::from))
+                .map_err(|e| e.to_string());
+        }
+
+        let (address, tls_dns_name) = match address.split_once('#') {
+            Some((address, name)) => (address, Some(name.to_string())),
+            None => (address, None),
+        };
+        let address = if protocol == NameserverProtocol::Https {
+            // trust-dns always sends the queries to the standard path
+            match address.split_once('/') {
+                Some((address, "dns-query")) | Some((address, "")) => address,
+                Some(_) => {
+                    return Err("Only the /dns-query path is supported for DNS-over-HTTPS".into())
+                }
+                None => address,
+            }
+        } else {
+            address
+        };
+
+        let default_port = if protocol == NameserverProtocol::Tls {
+            853
+        } else {
+            443
+        };
+        let (host, port) = split_host_port(address, default_port)?;
+        let (socket_addr, tls_dns_name) = match host.parse::<IpAddr>() {
+            Ok(ip) => match tls_dns_name {
+                Some(name) => (SocketAddr::new(ip, port), name),
+                None => {
+                    return Err(
+                        "The TLS name of the server is needed, add it after a #, like tls://1.1.1.1#cloudflare-dns.com"
+                            .into(),
+                    )
+                }
+            },
+            Err(_) => (
+                resolve_host(host, port)?,
+                tls_dns_name.unwrap_or_else(|| host.to_string()),
+            ),
+        };
+
+        Ok(Nameserver {
+            socket_addr,
+            protocol,
+            tls_dns_name: Some(tls_dns_name),
+        })
+    }
+}
+
+impl fmt::Display for Nameserver {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = self.tls_dns_name.as_deref().unwrap_or_default();
+        match self.protocol {
+            NameserverProtocol::Plain => write!(f, "{}", self.socket_addr),
+            NameserverProtocol::Tls => write!(f, "tls://{}#{}", self.socket_addr, name),
+            NameserverProtocol::Https => {
+                write!(f, "https://{}/dns-query#{}", self.socket_addr, name)
+            }
+        }
+    }
+}
+
+fn split_host_port(address: &str, default_port: u16) -> Result<(&str, u16), String> {
+    if let Ok(socket_addr) = address.parse::<SocketAddr>() {
+        let host = address
+            .rsplit_once(':')
+            .map(|(host, _)| host.trim_start_matches('[').trim_end_matches(']'))
+            .unwrap_or(address);
+        return Ok((host, socket_addr.port()));
+    }
+    if address.parse::<IpAddr>().is_ok() {
+        return Ok((address, default_port));
+    }
+    match address.rsplit_once(':') {
+        Some((host, port)) => Ok((
+            host,
+            port.parse()
+                .map_err(|_| format!("Invalid port in {}", address))?,
+        )),
+        None => Ok((address, default_port)),
+    }
+}
+
+fn resolve_host(host: &str, port: u16) -> Result<SocketAddr, String> {
+    let addresses: Vec<SocketAddr> = (host, port)
+        .to_socket_addrs()
+        .map_err(|e| format!("Can not resolve {}: {}", host, e))?
+        .collect();
+    addresses
+        .iter()
+        .find(|address| address.is_ipv4())
+        .or_else(|| addresses.first())
+        .copied()
+        .ok_or_else(|| format!("Can not resolve {}", host))
+}
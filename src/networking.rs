@@ -1,68 +1,343 @@
 use {
-    crate::{files, structs::Args},
-    std::{collections::HashSet, net::SocketAddr},
+    crate::{
+        errors::*,
+        nameservers::{Nameserver, NameserverProtocol},
+        structs::{ResolvData, ResolveFailure, ResolverCheck, ScanConfig},
+    },
+    rand::{distributions::Alphanumeric, Rng},
+    std::{
+        collections::{BTreeMap, HashMap, HashSet},
+        str::FromStr,
+        time::{Duration, Instant},
+    },
     trust_dns_resolver::{
         config::{NameServerConfig, NameServerConfigGroup, Protocol, ResolverConfig, ResolverOpts},
-        Resolver,
+        error::{ResolveError, ResolveErrorKind},
+        proto::{
+            error::ProtoErrorKind,
+            op::ResponseCode,
+            rr::{RData, Record, RecordType},
+        },
+        system_conf, Name, Resolver,
     },
 };
 
-pub fn get_records(resolver: &Resolver, domain: &str) -> String {
-    if let Ok(ips) = resolver.ipv4_lookup(domain) {
-        ips.iter()
-            .map(|x| x.to_string())
-            .next()
-            .expect("Failed to get IPV4.")
+// Wildcards answering with a rotating set of IPs need more than one probe
+const WILDCARD_PROBES: usize = 3;
+
+// Longer chains are most likely a loop
+const MAX_CNAME_CHAIN: usize = 16;
+
+pub fn get_records(resolver: &Resolver, domain: &str) -> Vec<String> {
+    if let Ok(ips) = resolver.lookup_ip(domain) {
+        let mut ips: Vec<String> = ips.iter().map(|x| x.to_string()).collect();
+        ips.sort();
+        ips.dedup();
+        ips
     } else {
-        String::new()
+        Vec::new()
     }
 }
 
-pub fn get_resolver(nameserver_ips: HashSet<SocketAddr>, opts: ResolverOpts) -> Resolver {
-    let mut name_servers = NameServerConfigGroup::with_capacity(nameserver_ips.len() * 2);
-    name_servers.extend(nameserver_ips.into_iter().flat_map(|socket_addr| {
-        std::iter::once(NameServerConfig {
-            socket_addr,
-            protocol: Protocol::Udp,
-            tls_dns_name: None,
-            trust_nx_responses: false,
+/// IPs of `domain` and the CNAME chain followed to get them. When there is no
+/// answer the CNAMEs are queried one by one so dangling chains are recorded.
+pub fn get_resolv_data(resolver: &Resolver, domain: &str, servfail_retries: usize) -> ResolvData {
+    let mut answer = resolver.lookup_ip(domain);
+    for _ in 0..servfail_retries {
+        match &answer {
+            Err(e) if return_resolve_failure(e) == ResolveFailure::Servfail => {
+                answer = resolver.lookup_ip(domain)
+            }
+            _ => break,
+        }
+    }
+
+    match answer {
+        Ok(lookup) => {
+            let mut ips: Vec<String> = lookup.iter().map(|x| x.to_string()).collect();
+            ips.sort();
+            ips.dedup();
+            ResolvData {
+                ips,
+                cname_chain: return_cname_chain(domain, lookup.as_lookup().record_iter()),
+                ..Default::default()
+            }
+        }
+        Err(e) => {
+            let cname_chain = get_cname_chain(resolver, domain);
+            ResolvData {
+                dangling_cname: !cname_chain.is_empty(),
+                cname_chain,
+                failure: Some(return_resolve_failure(&e)),
+                ..Default::default()
+            }
+        }
+    }
+}
+
+fn return_resolve_failure(error: &ResolveError) -> ResolveFailure {
+    match error.kind() {
+        ResolveErrorKind::NoRecordsFound { response_code, .. } => match *response_code {
+            ResponseCode::NXDomain => ResolveFailure::Nxdomain,
+            ResponseCode::ServFail => ResolveFailure::Servfail,
+            ResponseCode::Refused => ResolveFailure::Refused,
+            ResponseCode::NoError => ResolveFailure::NoAddress,
+            _ => ResolveFailure::Other,
+        },
+        ResolveErrorKind::Timeout => ResolveFailure::Timeout,
+        ResolveErrorKind::Proto(e) if matches!(e.kind(), ProtoErrorKind::Timeout) => {
+            ResolveFailure::Timeout
+        }
+        _ => ResolveFailure::Other,
+    }
+}
+
+/// Resolves `domain` through every resolver and only keeps the IPs and the
+/// CNAME chain returned by at least `quorum` of them. The IPs without enough
+/// votes are recorded as unconfirmed and never scanned.
+pub fn get_consensus_resolv_data(
+    resolvers: &[&Resolver],
+    domain: &str,
+    quorum: usize,
+    servfail_retries: usize,
+) -> ResolvData {
+    let answers: Vec<ResolvData> = resolvers
+        .iter()
+        .map(|resolver| get_resolv_data(resolver, domain, servfail_retries))
+        .collect();
+
+    let mut ip_votes: BTreeMap<&String, usize> = BTreeMap::new();
+    let mut cname_votes: HashMap<&Vec<String>, usize> = HashMap::new();
+    let mut failure_votes: HashMap<ResolveFailure, usize> = HashMap::new();
+    for answer in &answers {
+        for ip in &answer.ips {
+            *ip_votes.entry(ip).or_default() += 1
+        }
+        *cname_votes.entry(&answer.cname_chain).or_default() += 1;
+        if let Some(failure) = answer.failure {
+            *failure_votes.entry(failure).or_default() += 1
+        }
+    }
+
+    let cname_chain = cname_votes
+        .into_iter()
+        .max_by_key(|(_, votes)| *votes)
+        .filter(|(_, votes)| *votes >= quorum)
+        .map(|(cname_chain, _)| cname_chain.clone())
+        .unwrap_or_default();
+    let ips: Vec<String> = ip_votes
+        .iter()
+        .filter(|(_, votes)| **votes >= quorum)
+        .map(|(ip, _)| ip.to_string())
+        .collect();
+    ResolvData {
+        failure: if ips.is_empty() {
+            failure_votes
+                .into_iter()
+                .max_by_key(|(_, votes)| *votes)
+                .map(|(failure, _)| failure)
+        } else {
+            None
+        },
+        ips,
+        unconfirmed_ips: ip_votes
+            .iter()
+            .filter(|(_, votes)| **votes < quorum)
+            .map(|(ip, _)| ip.to_string())
+            .collect(),
+        dangling_cname: !cname_chain.is_empty()
+            && answers
+                .iter()
+                .filter(|answer| answer.dangling_cname)
+                .count()
+                >= quorum,
+        cname_chain,
+        ..Default::default()
+    }
+}
+
+fn return_cname_chain<'a>(domain: &str, records: impl Iterator<Item = &'a Record>) -> Vec<String> {
+    let cnames: HashMap<String, String> = records
+        .filter_map(|record| match record.rdata() {
+            RData::CNAME(target) => Some((
+                return_name_string(&record.name().to_string()),
+                return_name_string(&target.to_string()),
+            )),
+            _ => None,
         })
-        .chain(std::iter::once(NameServerConfig {
-            socket_addr,
-            protocol: Protocol::Tcp,
-            tls_dns_name: None,
-            trust_nx_responses: false,
-        }))
-    }));
-    Resolver::new(ResolverConfig::from_parts(None, vec![], name_servers), opts).unwrap()
+        .collect();
+    let mut cname_chain = Vec::new();
+    let mut name = return_name_string(domain);
+    while let Some(target) = cnames.get(&name) {
+        if cname_chain.len() == MAX_CNAME_CHAIN {
+            break;
+        }
+        cname_chain.push(target.clone());
+        name = target.clone();
+    }
+    cname_chain
 }
 
-pub fn return_socket_address(args: &Args) -> HashSet<SocketAddr> {
-    let mut resolver_ips = HashSet::new();
-    if args.custom_resolvers {
-        for r in &files::return_file_targets(args, args.resolvers.clone()) {
-            let server = r.to_owned() + ":53";
-            let socket_addr = SocketAddr::V4(match server.parse() {
-                Ok(a) => a,
-                Err(e) => unreachable!(
-                    "Error parsing the server {}, only IPv4 are allowed. Error: {}",
-                    r, e
-                ),
+fn get_cname_chain(resolver: &Resolver, domain: &str) -> Vec<String> {
+    let mut cname_chain = Vec::new();
+    let mut name = domain.to_string();
+    while cname_chain.len() < MAX_CNAME_CHAIN {
+        let target = resolver
+            .lookup(name.as_str(), RecordType::CNAME)
+            .ok()
+            .and_then(|lookup| {
+                lookup.iter().find_map(|rdata| match rdata {
+                    RData::CNAME(target) => Some(target.to_string()),
+                    _ => None,
+                })
             });
-            resolver_ips.insert(socket_addr);
+        match target {
+            Some(target) => {
+                cname_chain.push(return_name_string(&target));
+                name = target
+            }
+            None => break,
         }
-    } else {
-        for r in &args.resolvers {
-            let server = r.to_owned() + ":53";
-            let socket_addr = SocketAddr::V4(match server.parse() {
-                Ok(a) => a,
-                Err(e) => unreachable!(
-                    "Error parsing the server {}, only IPv4 are allowed. Error: {}",
-                    r, e
-                ),
-            });
-            resolver_ips.insert(socket_addr);
+    }
+    cname_chain
+}
+
+fn return_name_string(name: &str) -> String {
+    name.trim_end_matches('.').to_lowercase()
+}
+
+/// Resolves a few random labels under `domain`. Any answer means the domain
+/// has wildcard DNS and the IPs returned are the ones of the wildcard.
+pub fn get_wildcard_ips(resolver: &Resolver, domain: &str) -> HashSet<String> {
+    (0..WILDCARD_PROBES)
+        .flat_map(|_| get_records(resolver, &format!("{}.{}.", return_random_label(), domain)))
+        .collect()
+}
+
+/// Checks that `nameserver` resolves `domain` in less than `max_latency` and
+/// answers NXDOMAIN for a random label under it.
+pub fn check_resolver(
+    nameserver: Nameserver,
+    opts: ResolverOpts,
+    domain: &str,
+    max_latency: Duration,
+) -> ResolverCheck {
+    let resolver = get_resolver(std::iter::once(nameserver.clone()).collect(), &[], opts);
+    let domain = domain.trim_end_matches('.');
+    let mut check = ResolverCheck {
+        resolver: nameserver.to_string(),
+        ..Default::default()
+    };
+
+    let start = Instant::now();
+    let answer = resolver.lookup_ip(format!("{}.", domain).as_str());
+    let latency = start.elapsed();
+    check.latency_ms = Some(latency.as_millis() as u64);
+    check.problem = match answer {
+        Err(e) => Some(format!("it can't resolve {}: {}", domain, e)),
+        Ok(_) if latency > max_latency => Some(format!(
+            "it took {} ms to answer, more than the limit of {} ms",
+            latency.as_millis(),
+            max_latency.as_millis()
+        )),
+        Ok(_) => {
+            let name = format!("{}.{}.", return_random_label(), domain);
+            match resolver.lookup_ip(name.as_str()) {
+                Ok(_) => Some(String::from(
+                    "it answers for non-existent domains (NXDOMAIN hijacking)",
+                )),
+                Err(e) => match e.kind() {
+                    ResolveErrorKind::NoRecordsFound {
+                        response_code: ResponseCode::NXDomain,
+                        ..
+                    } => None,
+                    _ => Some(format!(
+                        "it doesn't answer NXDOMAIN for non-existent domains: {}",
+                        e
+                    )),
+                },
+            }
         }
+    };
+    check
+}
+
+fn return_random_label() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(16)
+        .map(char::from)
+        .collect::<String>()
+        .to_lowercase()
+}
+
+pub fn get_resolver(
+    nameservers: HashSet<Nameserver>,
+    search_domains: &[String],
+    opts: ResolverOpts,
+) -> Resolver {
+    let mut name_servers = NameServerConfigGroup::with_capacity(nameservers.len() * 2);
+    name_servers.extend(nameservers.iter().flat_map(return_nameserver_configs));
+    let search = search_domains
+        .iter()
+        .filter_map(|domain| Name::from_str(domain).ok())
+        .collect();
+    Resolver::new(ResolverConfig::from_parts(None, search, name_servers), opts).unwrap()
+}
+
+/// Nameservers, search domains and ndots of the system configuration, read
+/// from `/etc/resolv.conf` on Unix.
+pub fn return_system_config() -> Result<(Vec<Nameserver>, Vec<String>, usize)> {
+    let (config, opts) =
+        system_conf::read_system_conf().map_err(|e| UnimapError::SystemResolvers {
+            description: e.to_string(),
+        })?;
+    let mut nameservers: Vec<Nameserver> = config
+        .name_servers()
+        .iter()
+        .map(|name_server| Nameserver::from(name_server.socket_addr))
+        .collect();
+    // Every nameserver is listed once for UDP and once for TCP
+    nameservers.dedup();
+    if nameservers.is_empty() {
+        return Err(UnimapError::SystemResolvers {
+            description: String::from("there are no nameservers"),
+        }
+        .into());
     }
-    resolver_ips
+    let search_domains = config
+        .domain()
+        .into_iter()
+        .chain(config.search())
+        .map(|domain| domain.to_string())
+        .collect();
+    Ok((nameservers, search_domains, opts.ndots))
+}
+
+fn return_nameserver_configs(nameserver: &Nameserver) -> Vec<NameServerConfig> {
+    let protocols = match nameserver.protocol {
+        NameserverProtocol::Plain => vec![Protocol::Udp, Protocol::Tcp],
+        #[cfg(feature = "dns-over-tls")]
+        NameserverProtocol::Tls => vec![Protocol::Tls],
+        #[cfg(feature = "dns-over-https")]
+        NameserverProtocol::Https => vec![Protocol::Https],
+        // These entries are rejected when parsing the resolvers
+        #[allow(unreachable_patterns)]
+        _ => Vec::new(),
+    };
+    protocols
+        .into_iter()
+        .map(|protocol| NameServerConfig {
+            socket_addr: nameserver.socket_addr,
+            protocol,
+            tls_dns_name: nameserver.tls_dns_name.clone(),
+            trust_nx_responses: false,
+            #[cfg(any(feature = "dns-over-tls", feature = "dns-over-https"))]
+            tls_config: None,
+        })
+        .collect()
+}
+
+pub fn return_nameservers(config: &ScanConfig) -> HashSet<Nameserver> {
+    config.resolvers.iter().cloned().collect()
 }
@@ -1,18 +1,20 @@
 use {
-    crate::resolver_engine,
+    crate::{
+        errors::*,
+        scanners::{return_batch_label, split_batch_output, write_batch_list},
+        structs::ScanConfig,
+    },
     log::error,
-    std::{path::Path, process::Command},
+    serde::Deserialize,
+    std::{
+        fs,
+        io::ErrorKind,
+        net::{IpAddr, Ipv6Addr},
+        path::Path,
+        process::Command,
+    },
 };
 
-lazy_static! {
-    static ref NMAP_DNS_RESOLVERS: String = resolver_engine::RESOLVERS
-        .clone()
-        .iter()
-        .map(ToString::to_string)
-        .collect::<Vec<String>>()
-        .join(",");
-}
-
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Root {
@@ -23,23 +25,172 @@ pub struct Root {
 #[serde(rename_all = "camelCase")]
 pub struct Nmaprun {
     pub scanner: String,
+    #[serde(default)]
     pub args: String,
+    #[serde(default)]
     pub start: String,
+    #[serde(default)]
     pub startstr: String,
     pub version: String,
+    #[serde(rename = "profile_name")]
+    pub profile_name: Option<String>,
     pub xmloutputversion: String,
-    pub host: Option<Host>,
+    #[serde(default)]
+    pub scaninfo: Vec<Scaninfo>,
+    pub verbose: Option<Verbose>,
+    pub debugging: Option<Verbose>,
+    #[serde(default)]
+    pub target: Vec<Target>,
+    #[serde(default)]
+    pub taskbegin: Vec<Task>,
+    #[serde(default)]
+    pub taskprogress: Vec<Taskprogress>,
+    #[serde(default)]
+    pub taskend: Vec<Task>,
+    #[serde(default)]
+    pub hosthint: Vec<Hosthint>,
+    pub prescript: Option<Scripts>,
+    #[serde(default)]
+    pub host: Vec<Host>,
+    pub postscript: Option<Scripts>,
+    #[serde(default)]
+    pub output: Vec<Output>,
+    pub runstats: Option<Runstats>,
+}
+
+impl Nmaprun {
+    /// Returns the data of the given IP, runs with several IPs have a host
+    /// element for each one. Hosts without open ports are left out by
+    /// `--open`, so the host is always matched by its address.
+    pub fn return_host(&self, ip: &str) -> Option<&Host> {
+        let ip = ip.parse::<IpAddr>().ok()?;
+        self.host.iter().find(|host| {
+            host.address.iter().any(|address| {
+                address
+                    .addr
+                    .as_ref()
+                    .and_then(|addr| addr.parse::<IpAddr>().ok())
+                    == Some(ip)
+            })
+        })
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Scaninfo {
+    #[serde(rename = "type")]
+    pub type_field: String,
+    pub scanflags: Option<String>,
+    pub protocol: String,
+    pub numservices: String,
+    pub services: String,
+}
+
+/// Used for both the verbose and debugging elements.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Verbose {
+    pub level: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Target {
+    pub specification: String,
+    pub status: Option<String>,
+    pub reason: Option<String>,
+}
+
+/// Used for both the taskbegin and taskend elements.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Task {
+    pub task: String,
+    pub time: String,
+    pub extrainfo: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Taskprogress {
+    pub task: String,
+    pub time: String,
+    pub percent: String,
+    pub remaining: Option<String>,
+    pub etc: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Hosthint {
+    pub status: Status,
+    #[serde(default)]
+    pub address: Vec<Address>,
+    pub hostnames: Option<Hostnames>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Output {
+    #[serde(rename = "type")]
+    pub type_field: Option<String>,
+    #[serde(rename(deserialize = "$value"), default)]
+    pub value: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Runstats {
+    pub finished: Finished,
+    pub hosts: HostsStats,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Finished {
+    pub time: String,
+    pub timestr: Option<String>,
+    pub elapsed: String,
+    pub summary: Option<String>,
+    pub exit: Option<String>,
+    pub errormsg: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HostsStats {
+    pub up: String,
+    pub down: String,
+    pub total: String,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Host {
+    #[serde(default)]
     pub starttime: String,
+    #[serde(default)]
     pub endtime: String,
+    pub timedout: Option<String>,
+    pub comment: Option<String>,
     pub status: Status,
-    pub address: Address,
+    #[serde(default)]
+    pub address: Vec<Address>,
+    #[serde(default)]
     pub hostnames: Hostnames,
+    #[serde(default)]
+    pub smurf: Vec<Smurf>,
     pub ports: Option<Ports>,
+    pub os: Option<Os>,
+    pub distance: Option<Distance>,
+    pub uptime: Option<Uptime>,
+    pub tcpsequence: Option<Tcpsequence>,
+    pub ipidsequence: Option<Ipidsequence>,
+    pub tcptssequence: Option<Ipidsequence>,
+    pub hostscript: Option<Scripts>,
+    pub trace: Option<Trace>,
+    pub times: Option<Times>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -56,12 +207,14 @@ pub struct Status {
 pub struct Address {
     pub addr: Option<String>,
     pub addrtype: Option<String>,
+    pub vendor: Option<String>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Hostnames {
-    pub hostname: Option<Hostname>,
+    #[serde(default)]
+    pub hostname: Vec<Hostname>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -72,19 +225,50 @@ pub struct Hostname {
     pub type_field: String,
 }
 
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Smurf {
+    pub responses: String,
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Ports {
+    #[serde(default)]
+    pub extraports: Vec<Extraports>,
+    #[serde(default)]
     pub port: Vec<Port>,
 }
 
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Extraports {
+    pub state: String,
+    pub count: String,
+    #[serde(default)]
+    pub extrareasons: Vec<Extrareasons>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Extrareasons {
+    pub reason: String,
+    pub count: String,
+    pub proto: Option<String>,
+    pub ports: Option<String>,
+}
+
 #[derive(Default, Debug, Clone, Eq, PartialEq, Serialize, Deserialize, Hash)]
 #[serde(rename_all = "camelCase")]
 pub struct Port {
     pub protocol: String,
     pub portid: String,
     pub state: State,
+    #[serde(default)]
+    pub owner: Option<Owner>,
     pub service: Option<Service>,
+    #[serde(default)]
+    pub script: Vec<Script>,
 }
 
 #[derive(Default, Debug, Clone, Eq, PartialEq, Serialize, Deserialize, Hash)]
@@ -94,6 +278,14 @@ pub struct State {
     pub reason: String,
     #[serde(rename = "reason_ttl")]
     pub reason_ttl: String,
+    #[serde(rename = "reason_ip", default)]
+    pub reason_ip: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, Eq, PartialEq, Serialize, Deserialize, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct Owner {
+    pub name: String,
 }
 
 #[derive(Default, Debug, Clone, Eq, PartialEq, Serialize, Deserialize, Hash)]
@@ -106,20 +298,217 @@ pub struct Service {
     pub ostype: Option<String>,
     pub version: Option<String>,
     pub extrainfo: Option<String>,
+    #[serde(default)]
+    pub tunnel: Option<String>,
+    #[serde(default)]
+    pub proto: Option<String>,
+    #[serde(default)]
+    pub rpcnum: Option<String>,
+    #[serde(default)]
+    pub lowver: Option<String>,
+    #[serde(default)]
+    pub highver: Option<String>,
+    #[serde(default)]
+    pub hostname: Option<String>,
+    #[serde(default)]
+    pub devicetype: Option<String>,
+    #[serde(default)]
+    pub servicefp: Option<String>,
+    #[serde(default)]
+    pub cpe: Vec<String>,
 }
 
-pub fn get_nmap_data(
-    filename: &str,
-    host: &str,
-    min_rate: &str,
-    ports: &str,
-    fast_scan: bool,
-) -> Result<Nmaprun, serde_xml_rs::Error> {
-    let min_rate = min_rate.to_string();
+/// NSE script output. The structured output is kept in `elem` and `table`,
+/// scripts without it only have the `output` text.
+#[derive(Default, Debug, Clone, Eq, PartialEq, Serialize, Deserialize, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct Script {
+    pub id: String,
+    #[serde(default)]
+    pub output: String,
+    #[serde(default)]
+    pub elem: Vec<Elem>,
+    #[serde(default)]
+    pub table: Vec<Table>,
+}
+
+#[derive(Default, Debug, Clone, Eq, PartialEq, Serialize, Deserialize, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct Table {
+    pub key: Option<String>,
+    #[serde(default)]
+    pub elem: Vec<Elem>,
+    #[serde(default)]
+    pub table: Vec<Table>,
+}
+
+#[derive(Default, Debug, Clone, Eq, PartialEq, Serialize, Deserialize, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct Elem {
+    pub key: Option<String>,
+    #[serde(rename(deserialize = "$value"), default)]
+    pub value: String,
+}
+
+/// Used for the prescript, postscript and hostscript elements.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Scripts {
+    #[serde(default)]
+    pub script: Vec<Script>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Os {
+    #[serde(default)]
+    pub portused: Vec<Portused>,
+    #[serde(default)]
+    pub osmatch: Vec<Osmatch>,
+    #[serde(default)]
+    pub osfingerprint: Vec<Osfingerprint>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Portused {
+    pub state: String,
+    pub proto: String,
+    pub portid: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Osmatch {
+    pub name: String,
+    pub accuracy: String,
+    pub line: String,
+    #[serde(default)]
+    pub osclass: Vec<Osclass>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Osclass {
+    #[serde(rename = "type")]
+    pub type_field: Option<String>,
+    pub vendor: String,
+    pub osfamily: String,
+    pub osgen: Option<String>,
+    pub accuracy: String,
+    #[serde(default)]
+    pub cpe: Vec<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Osfingerprint {
+    pub fingerprint: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Distance {
+    pub value: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Uptime {
+    pub seconds: String,
+    pub lastboot: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Tcpsequence {
+    pub index: String,
+    pub difficulty: String,
+    pub values: String,
+}
+
+/// Used for both the ipidsequence and tcptssequence elements.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Ipidsequence {
+    pub class: String,
+    pub values: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Trace {
+    pub proto: Option<String>,
+    pub port: Option<String>,
+    #[serde(default)]
+    pub hop: Vec<Hop>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Hop {
+    pub ttl: String,
+    pub rtt: Option<String>,
+    pub ipaddr: Option<String>,
+    pub host: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Times {
+    pub srtt: String,
+    pub rttvar: String,
+    pub to: String,
+}
+
+pub fn get_nmap_data(filename: &str, host: &str, config: &ScanConfig) -> Result<Nmaprun> {
+    let mut nmap_args = return_nmap_args(filename, config);
+
+    if host.parse::<Ipv6Addr>().is_ok() {
+        nmap_args.push("-6".to_string())
+    }
+
+    nmap_args.push(host.to_string());
+
+    run_nmap(&nmap_args, filename, host)?;
+    parse_nmap_file(filename, host)
+}
+
+/// Scans several IPs of the same version with a single nmap run, passing them
+/// with -iL. `batch` contains each IP with the filename where its XML data is
+/// expected, every one of them gets the XML data of the whole run.
+pub fn get_nmap_batch_data(batch: &[(String, String)], config: &ScanConfig) -> Result<Nmaprun> {
+    let label = return_batch_label(batch);
+    let first_ip = batch.first().map(|(ip, _)| ip.as_str()).unwrap_or_default();
+    let (list_filename, batch_filename) = write_batch_list("nmap", batch, config)?;
+
+    let mut nmap_args = return_nmap_args(&batch_filename, config);
+    if first_ip.parse::<Ipv6Addr>().is_ok() {
+        nmap_args.push("-6".to_string())
+    }
+    nmap_args.append(&mut vec!["-iL".to_string(), list_filename.clone()]);
+
+    let result = run_nmap(&nmap_args, &batch_filename, &label)
+        .and_then(|_| parse_nmap_file(&batch_filename, &label));
+    if fs::remove_file(&list_filename).is_err() {
+        error!("Error removing filename {}.", &list_filename)
+    }
+    let nmap_data = result?;
+    split_batch_output("nmap", batch, &batch_filename)?;
+    Ok(nmap_data)
+}
+
+fn return_nmap_args(filename: &str, config: &ScanConfig) -> Vec<String> {
+    // Nmap only speaks plain DNS, the system resolver is used if there is no
+    // plain nameserver
+    let dns_servers = config
+        .resolvers
+        .iter()
+        .filter(|nameserver| nameserver.is_plain())
+        .map(|nameserver| nameserver.socket_addr.ip().to_string())
+        .collect::<Vec<String>>()
+        .join(",");
     let mut nmap_args = vec![
-        "nmap",
-        "--dns-servers",
-        &NMAP_DNS_RESOLVERS,
         "-Pn",
         "-sS",
         "--open",
@@ -131,38 +520,77 @@ pub fn get_nmap_data(
         filename,
     ];
 
-    if !min_rate.is_empty() {
-        nmap_args.append(&mut vec!["--min-rate", &min_rate])
+    if !dns_servers.is_empty() {
+        nmap_args.append(&mut vec!["--dns-servers", &dns_servers])
+    }
+
+    if !config.min_rate.is_empty() {
+        nmap_args.append(&mut vec!["--min-rate", &config.min_rate])
     }
 
-    if fast_scan {
+    if config.fast_scan {
         nmap_args.append(&mut vec!["--host-timeout", "20m"])
     } else {
         nmap_args.append(&mut vec!["-sV"])
     }
 
-    if !ports.is_empty() {
-        nmap_args.append(&mut vec!["-p", ports])
+    if !config.ports.is_empty() {
+        nmap_args.append(&mut vec!["-p", &config.ports])
     }
 
-    nmap_args.push(host);
+    if !config.scripts.is_empty() {
+        nmap_args.append(&mut vec!["--script", &config.scripts]);
+        if !config.script_args.is_empty() {
+            nmap_args.append(&mut vec!["--script-args", &config.script_args])
+        }
+    }
+
+    nmap_args.into_iter().map(str::to_owned).collect()
+}
 
-    match Command::new("nmap").args(&nmap_args).output() {
-        Ok(_) => {
-            if Path::new(&filename).exists() && Path::new(&filename).is_file() {
-                serde_xml_rs::from_str(&std::fs::read_to_string(filename).unwrap_or_default())
+fn run_nmap(nmap_args: &[String], filename: &str, host: &str) -> Result<()> {
+    match Command::new("nmap").args(nmap_args).output() {
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if stderr.contains("requires root privileges") {
+                Err(UnimapError::InsufficientPrivileges.into())
+            } else if Path::new(&filename).exists() && Path::new(&filename).is_file() {
+                Ok(())
             } else {
-                error!("Error executing nmap, possible causes: Nmap is not installed or you need root/administrator permissions. Leaving.\n");
-                println!();
-                std::process::exit(1)
+                Err(UnimapError::NmapFailed {
+                    ip: host.to_string(),
+                    description: stderr.trim().to_string(),
+                }
+                .into())
             }
         }
-        Err(e) => {
-            error!(
-                    "Error waiting command to finish for {}, continuing with remaining hosts. Description: {}",
-                    host, e
-                );
-            Ok(Nmaprun::default())
+        Err(e) if e.kind() == ErrorKind::NotFound => Err(UnimapError::NmapNotFound.into()),
+        Err(e) => Err(UnimapError::NmapFailed {
+            ip: host.to_string(),
+            description: e.to_string(),
         }
+        .into()),
     }
 }
+
+pub fn parse_nmap_file(filename: &str, host: &str) -> Result<Nmaprun> {
+    if !Path::new(filename).is_file() {
+        return Err(UnimapError::XmlNotFound {
+            ip: host.to_string(),
+            file: filename.to_string(),
+        }
+        .into());
+    }
+    let data = std::fs::read_to_string(filename).unwrap_or_default();
+    // Nmap mixes the children of nmaprun, e.g. the host elements of runs with
+    // several IPs come between hosthint and taskprogress elements
+    let mut deserializer = serde_xml_rs::Deserializer::new_from_reader(data.as_bytes())
+        .non_contiguous_seq_elements(true);
+    Nmaprun::deserialize(&mut deserializer).map_err(|e| {
+        UnimapError::XmlParse {
+            ip: host.to_string(),
+            description: e.to_string(),
+        }
+        .into()
+    })
+}
@@ -0,0 +1,364 @@
+use {
+    crate::{
+        errors::*,
+        files, logic,
+        nmap::{Port, Script},
+        structs::{Args, IpScanResult, PortRecord, ResolvData, ScanReport},
+    },
+    log::error,
+    prettytable,
+    prettytable::Table,
+    std::{
+        collections::{BTreeSet, HashSet},
+        fs::File,
+        io::Write,
+        sync::Mutex,
+    },
+};
+
+/// Prints the raw, URL or NDJSON lines of every IP as soon as its scan
+/// finishes, fanned out to all the hosts that resolved to it.
+pub struct StreamPrinter<'a> {
+    args: &'a Args,
+    printed_urls: Mutex<HashSet<String>>,
+    output_file: Mutex<Option<File>>,
+}
+
+impl<'a> StreamPrinter<'a> {
+    pub fn new(args: &'a Args) -> Result<StreamPrinter<'a>> {
+        let output_file = if args.jsonl_output {
+            files::return_output_file(args)?
+        } else {
+            None
+        };
+        print_header(args);
+        Ok(StreamPrinter {
+            args,
+            printed_urls: Mutex::new(HashSet::new()),
+            output_file: Mutex::new(output_file),
+        })
+    }
+
+    pub fn print(&self, result: &IpScanResult) {
+        let mut lines = String::new();
+        for host in &result.hosts {
+            for port_data in &result.ports {
+                if self.args.raw_output {
+                    lines.push_str(&return_raw_line(
+                        host,
+                        &result.ip,
+                        port_data,
+                        &result.host_scripts,
+                    ));
+                } else if self.args.url_output {
+                    let url = format!("{}:{}", host, port_data.portid);
+                    if !self.printed_urls.lock().unwrap().insert(url.clone()) {
+                        continue;
+                    }
+                    lines.push_str(&url);
+                } else if let Ok(line) = serde_json::to_string(&PortRecord {
+                    host: host.clone(),
+                    ip: result.ip.clone(),
+                    port: port_data.clone(),
+                }) {
+                    lines.push_str(&line);
+                }
+                lines.push('\n');
+            }
+        }
+        print!("{}", lines);
+
+        if let Some(file) = self.output_file.lock().unwrap().as_mut() {
+            if file.write_all(lines.as_bytes()).is_err() && !self.args.quiet_flag {
+                error!(
+                    "An error occurred while writing the output file {}.\n",
+                    self.args.file_name
+                )
+            }
+        }
+    }
+
+    /// Writes the parts of the report that can't be streamed.
+    pub fn finish(&self, report: &ScanReport) -> Result<()> {
+        if !self.args.jsonl_output {
+            write_table_file(self.args, &return_table(report))?
+        }
+        Ok(())
+    }
+}
+
+pub fn print_report(args: &Args, report: &ScanReport) -> Result<()> {
+    if args.json_output {
+        let json = serde_json::to_string_pretty(report)?;
+        println!("{}", json);
+        write_output_file(args, json + "\n")?;
+        return Ok(());
+    } else if args.jsonl_output {
+        let mut jsonl = String::new();
+        for record in return_port_records(report) {
+            jsonl.push_str(&serde_json::to_string(&record)?);
+            jsonl.push('\n');
+        }
+        print!("{}", jsonl);
+        write_output_file(args, jsonl)?;
+        return Ok(());
+    } else if args.raw_output {
+        print_raw(args, report)
+    } else if args.url_output {
+        print_urls(args, report)
+    }
+
+    let table = return_table(report);
+    write_table_file(args, &table)?;
+    if !args.quiet_flag && !args.raw_output && !args.url_output {
+        table.printstd();
+    }
+    Ok(())
+}
+
+fn write_table_file(args: &Args, table: &Table) -> Result<()> {
+    if table.is_empty() {
+        return Ok(());
+    }
+    if let Some(file) = files::return_output_file(args)? {
+        if files::table_to_file(table, file).is_err() && !args.quiet_flag {
+            error!(
+                "An error occurred while writing the output file {}.\n",
+                args.file_name
+            )
+        }
+    }
+    Ok(())
+}
+
+fn print_header(args: &Args) {
+    if args.quiet_flag {
+        return;
+    }
+    if args.raw_output {
+        println!("HOST,IP,PORT,SERVICE,VERSION,PRODUCT,OS,EXTRAINFO,SCRIPTS,HOSTSCRIPTS")
+    } else if args.url_output {
+        println!("HOST:IP")
+    }
+}
+
+pub fn return_port_records(report: &ScanReport) -> Vec<PortRecord> {
+    report
+        .hosts
+        .iter()
+        .flat_map(|(target, resolv_data)| {
+            resolv_data
+                .ports_data
+                .iter()
+                .flat_map(move |(ip, ports_data)| {
+                    ports_data.iter().map(move |port_data| PortRecord {
+                        host: target.clone(),
+                        ip: ip.clone(),
+                        port: port_data.clone(),
+                    })
+                })
+        })
+        .collect()
+}
+
+fn write_output_file(args: &Args, data: String) -> Result<()> {
+    if let Some(file) = files::return_output_file(args)? {
+        if files::string_to_file(data, file).is_err() && !args.quiet_flag {
+            error!(
+                "An error occurred while writing the output file {}.\n",
+                args.file_name
+            )
+        }
+    }
+    Ok(())
+}
+
+pub fn print_raw(args: &Args, report: &ScanReport) {
+    print_header(args);
+    for (target, resolv_data) in &report.hosts {
+        for (ip, ports_data) in &resolv_data.ports_data {
+            let host_scripts = resolv_data
+                .host_scripts
+                .get(ip)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            for port_data in ports_data {
+                println!("{}", return_raw_line(target, ip, port_data, host_scripts))
+            }
+        }
+    }
+}
+
+pub fn print_urls(args: &Args, report: &ScanReport) {
+    print_header(args);
+    for (target, resolv_data) in &report.hosts {
+        let ports: BTreeSet<&String> = resolv_data
+            .ports_data
+            .values()
+            .flatten()
+            .map(|port_data| &port_data.portid)
+            .collect();
+        for port in ports {
+            println!("{}:{}", target, port)
+        }
+    }
+}
+
+fn return_raw_line(host: &str, ip: &str, port_data: &Port, host_scripts: &[Script]) -> String {
+    let service = port_data.service.clone().unwrap_or_default();
+    format!(
+        "{},{},{},{},{},{},{},{},{},{}",
+        host,
+        ip,
+        port_data.portid,
+        service.name,
+        null_checker(service.version),
+        null_checker(service.product),
+        null_checker(service.ostype),
+        null_checker(service.extrainfo),
+        return_scripts_field(&port_data.script),
+        return_scripts_field(host_scripts)
+    )
+}
+
+// Scripts output spans several lines and can contain commas, so it's joined
+// in a single line and quoted when needed.
+fn return_scripts_field(scripts: &[Script]) -> String {
+    if scripts.is_empty() {
+        return String::from("NULL");
+    }
+    let field = scripts
+        .iter()
+        .map(|script| {
+            format!(
+                "{}: {}",
+                script.id,
+                script
+                    .output
+                    .split_whitespace()
+                    .collect::<Vec<&str>>()
+                    .join(" ")
+            )
+        })
+        .collect::<Vec<String>>()
+        .join(" | ");
+    if field.contains(',') || field.contains('"') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
+
+pub fn return_table(report: &ScanReport) -> Table {
+    let mut table = Table::new();
+    table.set_titles(row![
+        bcFg => "HOST",
+       "IP",
+       "CNAME CHAIN",
+       "OPEN PORTS",
+       "SERVICES",
+       "WILDCARD"
+    ]);
+    for (target, resolv_data) in &report.hosts {
+        // Failed hosts are kept so the reason shows up in the IP column
+        if resolv_data.ips.is_empty()
+            && resolv_data.cname_chain.is_empty()
+            && resolv_data.unconfirmed_ips.is_empty()
+            && resolv_data.failure.is_none()
+        {
+            continue;
+        }
+        let mut services_table = Table::new();
+        for (ip, ports_data) in &resolv_data.ports_data {
+            for port_data in ports_data {
+                add_service_rows(&mut services_table, ip, port_data)
+            }
+            if let Some(host_scripts) = resolv_data.host_scripts.get(ip) {
+                services_table.add_row(row![bc => &format!("HOST SCRIPTS => {}", ip)]);
+                add_script_rows(&mut services_table, host_scripts)
+            }
+        }
+        table.add_row(row![ d =>
+            target,
+            return_ips_column(resolv_data),
+            return_cname_chain_string(resolv_data),
+            resolv_data
+                .ips
+                .iter()
+                .map(|ip| format!(
+                    "{}: {}",
+                    ip,
+                    logic::return_ports_string(
+                        &resolv_data
+                            .ports_data
+                            .get(ip)
+                            .map(|ports_data| ports_data
+                                .iter()
+                                .map(|f| f.portid.clone())
+                                .collect())
+                            .unwrap_or_default(),
+                    )
+                ))
+                .collect::<Vec<String>>()
+                .join("\n"),
+            services_table,
+            if resolv_data.wildcard { "YES" } else { "NO" },
+        ]);
+    }
+    table
+}
+
+fn return_ips_column(resolv_data: &ResolvData) -> String {
+    let ips = logic::return_ips_string(
+        &resolv_data
+            .ips
+            .iter()
+            .map(|ip| match resolv_data.special_ips.get(ip) {
+                Some(category) => format!("{} ({})", ip, category),
+                None => ip.clone(),
+            })
+            .chain(
+                resolv_data
+                    .unconfirmed_ips
+                    .iter()
+                    .map(|ip| format!("{} (UNCONFIRMED)", ip)),
+            )
+            .collect::<Vec<String>>(),
+    );
+    match resolv_data.failure {
+        Some(failure) if resolv_data.ips.is_empty() => format!("{} ({})", ips, failure),
+        _ => ips,
+    }
+}
+
+fn return_cname_chain_string(resolv_data: &ResolvData) -> String {
+    if resolv_data.cname_chain.is_empty() {
+        String::from("NULL")
+    } else if resolv_data.dangling_cname {
+        format!("{} (DANGLING)", resolv_data.cname_chain.join("\n-> "))
+    } else {
+        resolv_data.cname_chain.join("\n-> ")
+    }
+}
+
+fn add_service_rows(services_table: &mut Table, ip: &str, port_data: &Port) {
+    let service = port_data.service.clone().unwrap_or_default();
+    services_table.add_row(row![bc => &format!("PORT => {}:{}", ip, port_data.portid)]);
+    services_table.add_row(row![c => &format!("SERVICE: {}", service.name)]);
+    services_table.add_row(row![c => &format!("VERSION: {}", null_checker(service.version))]);
+    services_table.add_row(row![c => &format!("PRODUCT: {}", null_checker(service.product))]);
+    services_table.add_row(row![c => &format!("OS TYPE: {}", null_checker(service.ostype))]);
+    services_table.add_row(row![c => &format!("EXTRA INFO: {}", null_checker(service.extrainfo))]);
+    add_script_rows(services_table, &port_data.script);
+}
+
+fn add_script_rows(services_table: &mut Table, scripts: &[Script]) {
+    for script in scripts {
+        services_table
+            .add_row(row![c => &format!("SCRIPT {}: {}", script.id, script.output.trim())]);
+    }
+}
+
+fn null_checker(value: Option<String>) -> String {
+    value.unwrap_or_else(|| "NULL".to_string())
+}
@@ -1,19 +1,30 @@
 use {
     crate::{
-        args,
+        addresses::{classify_ip, Cidr, IpCategory},
         errors::*,
-        files, logic, networking,
-        nmap::{self, Nmaprun},
-        structs::{Args, ResolvData},
+        files, logic,
+        nameservers::Nameserver,
+        networking,
+        nmap::{Port, Script},
+        output,
+        scanners::ScannerBackend,
+        structs::{
+            Args, Exclusion, IpScanResult, ResolvData, ResolverCheck, ScanConfig, ScanError,
+            ScanReport, ScanState,
+        },
+        tcp_connect,
     },
+    chrono::Utc,
     log::{error, info},
-    prettytable,
-    prettytable::Table,
+    rand::seq::SliceRandom,
     rayon::prelude::*,
     std::{
-        collections::{HashMap, HashSet},
-        net::Ipv4Addr,
-        time::Duration,
+        collections::{BTreeMap, HashMap, HashSet},
+        io::Write,
+        net::IpAddr,
+        path::Path,
+        sync::Mutex,
+        time::Instant,
     },
     trust_dns_resolver::{
         config::{LookupIpStrategy, ResolverOpts},
@@ -21,168 +32,22 @@ use {
     },
 };
 
-lazy_static! {
-    pub static ref RESOLVERS: Vec<Ipv4Addr> = {
-        let args = args::get_args();
-        let mut resolver_ips = Vec::new();
-        if args.custom_resolvers {
-            for r in &files::return_file_targets(&args, args.resolvers.clone()) {
-                match r.parse::<Ipv4Addr>() {
-                    Ok(ip) => resolver_ips.push(ip),
-                    Err(e) => {
-                        error!("Error parsing the {} IP from resolvers file to IP address. Please check and try again. Error: {}\n", r, e);
-                        std::process::exit(1)
-                    }
-                }
-            }
-        } else {
-            for r in args.resolvers {
-                match r.parse::<Ipv4Addr>() {
-                    Ok(ip) => resolver_ips.push(ip),
-                    Err(e) => {
-                        error!("Error parsing the {} IP from resolvers file to IP address. Please check and try again. Error: {}\n", r, e);
-                        std::process::exit(1)
-                    }
-                }
-            }
-        }
-        resolver_ips
-    };
-}
-
-pub fn parallel_resolver_all(args: &mut Args) -> Result<()> {
-    files::check_full_path(&args.logs_dir);
-
+pub fn parallel_resolver_all(args: &Args, config: &ScanConfig) -> Result<()> {
     if !args.quiet_flag {
         info!(
             "Performing parallel resolution for {} targets with {} threads, it will take a while...\n",
-            args.targets.len(), args.threads
+            args.targets.len(), config.threads
         )
     }
 
-    let opts = ResolverOpts {
-        timeout: Duration::from_secs(1),
-        ip_strategy: LookupIpStrategy::Ipv4Only,
-        num_concurrent_reqs: 1,
-        ..Default::default()
-    };
-
-    let resolver = networking::get_resolver(networking::return_socket_address(args), opts);
-
-    let data = parallel_resolver_engine(args, args.targets.clone(), resolver);
-
-    let mut table = Table::new();
-    table.set_titles(row![
-        bcFg => "HOST",
-       "IP",
-       "OPEN PORTS",
-       "SERVICES"
-    ]);
-    if args.raw_output && !args.quiet_flag {
-        println!("HOST,IP,PORT,SERVICE,VERSION,PRODUCT,OS,EXTRAINFO")
-    } else if args.url_output && !args.quiet_flag {
-        println!("HOST:IP")
-    }
-    for (target, resolv_data) in &data {
-        if !resolv_data.ip.is_empty() {
-            if args.raw_output {
-                for port_data in &resolv_data.ports_data {
-                    println!(
-                        "{},{},{},{},{},{},{},{}",
-                        target,
-                        resolv_data.ip,
-                        port_data.portid,
-                        port_data.service.clone().unwrap_or_default().name,
-                        port_data
-                            .clone()
-                            .service
-                            .unwrap_or_default()
-                            .version
-                            .unwrap_or_else(|| "NULL".to_string()),
-                        port_data
-                            .clone()
-                            .service
-                            .clone()
-                            .unwrap_or_default()
-                            .product
-                            .unwrap_or_else(|| "NULL".to_string()),
-                        port_data
-                            .service
-                            .clone()
-                            .unwrap_or_default()
-                            .ostype
-                            .clone()
-                            .unwrap_or_else(|| "NULL".to_string()),
-                        port_data
-                            .service
-                            .clone()
-                            .unwrap_or_default()
-                            .extrainfo
-                            .clone()
-                            .unwrap_or_else(|| "NULL".to_string())
-                    )
-                }
-            } else if args.url_output {
-                for port_data in &resolv_data.ports_data {
-                    println!("{}:{}", target, port_data.portid)
-                }
-            } else {
-                let mut services_table = Table::new();
-                for port_data in &resolv_data.ports_data {
-                    services_table
-                        .add_row(row![bc => &format!("PORT => {}", port_data.portid.clone())]);
-                    services_table.add_row(
-                    row![c => &format!("SERVICE: {}", port_data.service.clone().unwrap_or_default().name)],
-                );
-                    services_table.add_row(row![c => &format!("VERSION: {}" ,port_data
-                .service.clone().unwrap_or_default()
-                .version
-                .clone()
-                .unwrap_or_else(|| "NULL".to_string()))]);
-                    services_table.add_row(row![c => &format!("PRODUCT: {}", port_data
-                    .service.clone().unwrap_or_default()
-                    .product
-                    .clone()
-                    .unwrap_or_else(|| "NULL".to_string()))]);
-                    services_table.add_row(row![c => &format!("OS TYPE: {}", port_data
-                    .service.clone().unwrap_or_default()
-                    .ostype
-                    .clone()
-                    .unwrap_or_else(|| "NULL".to_string()))]);
-                    services_table.add_row(row![c => &format!("EXTRA INFO: {}", port_data
-                    .service.clone().unwrap_or_default()
-                    .extrainfo
-                    .clone()
-                    .unwrap_or_else(|| "NULL".to_string()))]);
-                }
-                table.add_row(row![ d =>
-                    target,
-                    logic::null_ip_checker(&resolv_data.ip),
-                    logic::return_ports_string(
-                        &resolv_data
-                            .ports_data
-                            .iter()
-                            .map(|f| f.portid.clone())
-                            .collect(),
-                    ),
-                    services_table,
-                ]);
-            }
-        }
-    }
-
-    if args.with_output
-        && !args.targets.is_empty()
-        && files::table_to_file(&table, files::return_output_file(args)).is_err()
-        && !args.quiet_flag
-    {
-        error!(
-            "An error occurred while writing the output file {}.\n",
-            args.file_name
-        )
-    }
-    if !args.quiet_flag && !args.raw_output && !args.url_output {
-        table.printstd();
+    if args.raw_output || args.url_output || args.jsonl_output {
+        let printer = output::StreamPrinter::new(args)?;
+        let report =
+            scan_with_callback(config, args.targets.clone(), |result| printer.print(result))?;
+        printer.finish(&report)?;
+    } else {
+        let report = scan(config, args.targets.clone())?;
+        output::print_report(args, &report)?;
     }
 
     if (args.with_output || args.unique_output_flag) && !args.quiet_flag {
@@ -198,103 +63,656 @@ pub fn parallel_resolver_all(args: &mut Args) -> Result<()> {
     Ok(())
 }
 
-fn parallel_resolver_engine(
-    args: &Args,
+pub fn scan(config: &ScanConfig, targets: HashSet<String>) -> Result<ScanReport> {
+    scan_with_callback(config, targets, |_| ())
+}
+
+/// Same as `scan`, but `on_result` is called with the data of every IP as
+/// soon as its scan finishes, so results can be used while the run continues.
+pub fn scan_with_callback<F>(
+    config: &ScanConfig,
     targets: HashSet<String>,
-    resolver: Resolver,
-) -> HashMap<String, ResolvData> {
-    let resolv_data: HashMap<String, ResolvData> = targets
+    on_result: F,
+) -> Result<ScanReport>
+where
+    F: Fn(&IpScanResult) + Sync,
+{
+    // The other scanners check the ports themselves, the native one would
+    // fail the same way for every IP
+    if config.scanner == ScannerBackend::Native {
+        tcp_connect::return_port_list(&config.ports)?;
+    }
+    files::check_full_path(&config.logs_dir);
+
+    let started_at = Utc::now();
+    let time_wasted = Instant::now();
+
+    let mut nameservers = networking::return_nameservers(config);
+    // Resolvers are only needed when there are hostnames to resolve
+    if config.check_resolvers
+        && !config.resume
+        && config.hosts_map.is_none()
+        && targets
+            .iter()
+            .any(|target| target.parse::<IpAddr>().is_err() && target.parse::<Cidr>().is_err())
+    {
+        nameservers = return_healthy_resolvers(config, &nameservers)?
+    }
+
+    // Independent resolvers, one per nameserver, to ask each of them separately
+    let consensus_resolvers: Vec<Resolver> = if config.consensus > 1 {
+        nameservers
+            .iter()
+            .map(|nameserver| {
+                networking::get_resolver(
+                    std::iter::once(nameserver.clone()).collect(),
+                    &config.search_domains,
+                    return_resolver_opts(config),
+                )
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    if config.consensus > consensus_resolvers.len() && config.consensus > 1 && !config.quiet_flag {
+        info!(
+            "Only {} resolvers are available, they will be used for the consensus instead of {}.\n",
+            consensus_resolvers.len(),
+            config.consensus
+        )
+    }
+
+    let resolver = networking::get_resolver(
+        nameservers,
+        &config.search_domains,
+        return_resolver_opts(config),
+    );
+
+    let report = rayon::ThreadPoolBuilder::new()
+        .num_threads(config.threads)
+        .build()?
+        .install(|| {
+            parallel_resolver_engine(config, targets, resolver, &consensus_resolvers, &on_result)
+        })?;
+
+    Ok(ScanReport {
+        started_at: started_at.to_rfc3339(),
+        finished_at: Utc::now().to_rfc3339(),
+        elapsed_secs: time_wasted.elapsed().as_secs(),
+        ..report
+    })
+}
+
+/// Runs the health check of every resolver in parallel, the results are
+/// sorted by resolver.
+pub fn check_resolvers(
+    config: &ScanConfig,
+    nameservers: &HashSet<Nameserver>,
+) -> Vec<ResolverCheck> {
+    let opts = ResolverOpts {
+        attempts: 1,
+        use_hosts_file: false,
+        ..return_resolver_opts(config)
+    };
+    let mut checks: Vec<ResolverCheck> = nameservers
         .par_iter()
-        .map(|target| {
-            let fqdn_target = format!("{}.", target);
-            let mut resolv_data = ResolvData::default();
-            resolv_data.ip = networking::get_records(&resolver, &fqdn_target);
-            (target.to_owned(), resolv_data)
+        .map(|nameserver| {
+            networking::check_resolver(
+                nameserver.clone(),
+                opts,
+                &config.health_check_domain,
+                config.max_resolver_latency,
+            )
+        })
+        .collect();
+    checks.sort_by(|a, b| a.resolver.cmp(&b.resolver));
+    checks
+}
+
+fn return_healthy_resolvers(
+    config: &ScanConfig,
+    nameservers: &HashSet<Nameserver>,
+) -> Result<HashSet<Nameserver>> {
+    let checks = check_resolvers(config, nameservers);
+    let healthy: HashSet<Nameserver> = nameservers
+        .iter()
+        .filter(|nameserver| {
+            let resolver = nameserver.to_string();
+            checks
+                .iter()
+                .any(|check| check.resolver == resolver && check.is_healthy())
         })
+        .cloned()
         .collect();
 
+    if !config.quiet_flag {
+        for check in &checks {
+            if let Some(problem) = &check.problem {
+                info!("Dropping the resolver {}, {}.\n", check.resolver, problem)
+            }
+        }
+        info!(
+            "{} of {} resolvers passed the health check.\n",
+            healthy.len(),
+            checks.len()
+        )
+    }
+
+    if healthy.is_empty() {
+        Err(UnimapError::NoHealthyResolvers.into())
+    } else {
+        Ok(healthy)
+    }
+}
+
+fn return_resolver_opts(config: &ScanConfig) -> ResolverOpts {
+    ResolverOpts {
+        timeout: config.dns_timeout,
+        attempts: config.dns_attempts,
+        ip_strategy: if config.ipv6 {
+            LookupIpStrategy::Ipv4AndIpv6
+        } else {
+            LookupIpStrategy::Ipv4Only
+        },
+        num_concurrent_reqs: config.dns_concurrent_reqs,
+        ndots: config.ndots,
+        // Keeps the CNAME records in the answers to build the chains
+        preserve_intermediates: true,
+        ..Default::default()
+    }
+}
+
+fn parallel_resolver_engine(
+    config: &ScanConfig,
+    targets: HashSet<String>,
+    resolver: Resolver,
+    consensus_resolvers: &[Resolver],
+    on_result: &(dyn Fn(&IpScanResult) + Sync),
+) -> Result<ScanReport> {
+    let mut errors = Vec::new();
+
+    let previous_state = if config.resume {
+        files::return_scan_state(&config.logs_dir)?
+    } else {
+        ScanState::default()
+    };
+
+    let mut resolv_data: HashMap<String, ResolvData> = if config.resume {
+        let mut resolv_data = return_resolv_data(&previous_state.hosts);
+        for target in &previous_state.wildcards {
+            if let Some(resolv_data) = resolv_data.get_mut(target) {
+                resolv_data.wildcard = true
+            }
+        }
+        for (target, cname_chain) in &previous_state.cname_chains {
+            if let Some(resolv_data) = resolv_data.get_mut(target) {
+                resolv_data.dangling_cname = previous_state.dangling_cnames.contains(target);
+                resolv_data.cname_chain = cname_chain.clone()
+            }
+        }
+        for (target, failure) in &previous_state.failures {
+            if let Some(resolv_data) = resolv_data.get_mut(target) {
+                resolv_data.failure = Some(*failure)
+            }
+        }
+        for (target, unconfirmed_ips) in &previous_state.unconfirmed_ips {
+            if let Some(resolv_data) = resolv_data.get_mut(target) {
+                resolv_data.unconfirmed_ips = unconfirmed_ips.clone()
+            }
+        }
+        resolv_data
+    } else if let Some(hosts_map) = &config.hosts_map {
+        return_resolv_data(&files::return_hosts_map(hosts_map)?)
+    } else {
+        let mut resolv_data = resolve_targets(
+            config,
+            &targets,
+            &resolver,
+            consensus_resolvers,
+            &mut errors,
+        );
+        flag_wildcards(&resolver, &mut resolv_data);
+        resolv_data
+    };
+
+    let mut excluded = Vec::new();
+    resolv_data.retain(|target, resolv_data| {
+        // CIDR targets are expanded to their IPs
+        let reason = if let Ok(ip) = target.parse::<IpAddr>() {
+            config.scope.check_ip_target(&ip)
+        } else if resolv_data.wildcard && config.drop_wildcards {
+            Some("it only resolves to the wildcard DNS answer of its parent domain".to_string())
+        } else {
+            config.scope.check_host(target)
+        };
+        match reason {
+            Some(reason) => {
+                excluded.push(Exclusion {
+                    target: target.clone(),
+                    reason,
+                });
+                false
+            }
+            None => true,
+        }
+    });
+
     let mut nmap_ips: HashSet<String> = resolv_data
         .values()
-        .map(|resolv_data| resolv_data.ip.clone())
+        .flat_map(|resolv_data| resolv_data.ips.clone())
         .collect();
 
     let nmap_ips_orig = nmap_ips.clone();
 
-    nmap_ips.retain(|ip| {
-        !ip.is_empty()
-            && !ip.parse::<Ipv4Addr>().unwrap().is_private()
-            && ip.parse::<Ipv4Addr>().is_ok()
+    nmap_ips.retain(|ip| match ip.parse::<IpAddr>() {
+        Ok(parsed_ip) => match config.scope.check_ip(&parsed_ip) {
+            Some(reason) => {
+                excluded.push(Exclusion {
+                    target: ip.clone(),
+                    reason,
+                });
+                false
+            }
+            None => {
+                let category = classify_ip(&parsed_ip);
+                if parsed_ip.is_ipv6() && !config.ipv6 {
+                    excluded.push(Exclusion {
+                        target: ip.clone(),
+                        reason: "IPv6 scanning is disabled, use --ipv6".to_string(),
+                    });
+                    false
+                } else if category.is_scannable(config.include_internal) {
+                    true
+                } else {
+                    excluded.push(Exclusion {
+                        target: ip.clone(),
+                        reason: format!("it's a special-purpose address ({})", category),
+                    });
+                    false
+                }
+            }
+        },
+        Err(_) => false,
     });
 
     if nmap_ips.is_empty() {
-        error!(
-            "No valid IPs found for scanning. IPs found: {:?}\n",
-            nmap_ips_orig
-        );
-        std::process::exit(1)
+        // The exclusions are the reasons why there is nothing to scan
+        print_exclusions(config, &mut excluded);
+        let mut ips: Vec<String> = nmap_ips_orig.into_iter().collect();
+        ips.sort();
+        return Err(UnimapError::NoScannableIps { ips }.into());
+    }
+
+    let mut ip_hosts: HashMap<&String, Vec<String>> = HashMap::new();
+    for (target, resolv_data) in &resolv_data {
+        for ip in &resolv_data.ips {
+            ip_hosts.entry(ip).or_default().push(target.clone())
+        }
+    }
+
+    // The state isn't saved if the XML files are going to be removed, as
+    // there would be nothing to reuse when resuming, nor in offline mode
+    // where it could overwrite the state of a previous run.
+    let keep_state = !config.no_keep_nmap_logs && !config.offline;
+    let state = ScanState {
+        hosts: resolv_data
+            .iter()
+            .map(|(target, resolv_data)| (target.clone(), resolv_data.ips.clone()))
+            .collect(),
+        done_ips: previous_state.done_ips.clone(),
+        wildcards: resolv_data
+            .iter()
+            .filter(|(_, resolv_data)| resolv_data.wildcard)
+            .map(|(target, _)| target.clone())
+            .collect(),
+        cname_chains: resolv_data
+            .iter()
+            .filter(|(_, resolv_data)| !resolv_data.cname_chain.is_empty())
+            .map(|(target, resolv_data)| (target.clone(), resolv_data.cname_chain.clone()))
+            .collect(),
+        dangling_cnames: resolv_data
+            .iter()
+            .filter(|(_, resolv_data)| resolv_data.dangling_cname)
+            .map(|(target, _)| target.clone())
+            .collect(),
+        failures: resolv_data
+            .iter()
+            .filter_map(|(target, resolv_data)| Some((target.clone(), resolv_data.failure?)))
+            .collect(),
+        unconfirmed_ips: resolv_data
+            .iter()
+            .filter(|(_, resolv_data)| !resolv_data.unconfirmed_ips.is_empty())
+            .map(|(target, resolv_data)| (target.clone(), resolv_data.unconfirmed_ips.clone()))
+            .collect(),
+    };
+    // Finished IPs are appended to a log instead of saving the whole state
+    // after every scan
+    let done_ips_file = Mutex::new(if keep_state {
+        files::save_scan_state(&config.logs_dir, &state)?;
+        Some(files::return_done_ips_file(&config.logs_dir)?)
     } else {
-        let nmap_data: HashMap<String, Nmaprun> = nmap_ips
-            .par_iter()
-            .map(|ip| {
-                let filename = format!("{}/{}.xml", &args.logs_dir, &ip);
-                match nmap::get_nmap_data(
-                    &filename,
-                    ip,
-                    &args.min_rate,
-                    &args.ports,
-                    args.fast_scan,
-                ) {
-                    Ok(nmap_data) => {
-                        nmap_data
-                            .host
-                            .clone()
-                            .unwrap_or_default()
-                            .ports
-                            .unwrap_or_default()
-                            .port
-                            .retain(|f| f.state.state == "open");
-                        if args.no_keep_nmap_logs && std::fs::remove_file(&filename).is_err() {
-                            error!("Error removing filename {}.", &filename)
+        None
+    });
+    if config.resume && !config.quiet_flag {
+        info!(
+            "Resuming scan from {}, {} of {} IPs were already scanned.\n",
+            config.logs_dir,
+            nmap_ips
+                .iter()
+                .filter(|ip| previous_state.done_ips.contains(*ip))
+                .count(),
+            nmap_ips.len()
+        )
+    }
+
+    let scanner = config.scanner.port_scanner();
+    // IPv4 and IPv6 addresses can't be scanned by the same nmap run
+    let (ipv6_ips, ipv4_ips): (Vec<&String>, Vec<&String>) =
+        nmap_ips.iter().partition(|ip| ip.contains(':'));
+    let batches: Vec<Vec<&String>> = [ipv4_ips, ipv6_ips]
+        .iter()
+        .flat_map(|ips| {
+            ips.chunks(config.batch_size.max(1))
+                .map(<[&String]>::to_vec)
+        })
+        .collect();
+    let mut nmap_data: HashMap<String, Vec<Port>> = HashMap::new();
+    let mut host_scripts_data: HashMap<String, Vec<Script>> = HashMap::new();
+    for (ip, result) in batches
+        .par_iter()
+        .flat_map_iter(|batch| {
+            let mut results = Vec::new();
+            let mut pending = Vec::new();
+            for &ip in batch {
+                let filename =
+                    files::return_scan_filename(&config.logs_dir, ip, scanner.file_extension());
+                // Scope is checked again right before launching nmap so nothing
+                // out of scope is ever touched, whatever the source of the IPs.
+                let out_of_scope = ip
+                    .parse::<IpAddr>()
+                    .ok()
+                    .and_then(|parsed_ip| config.scope.check_ip(&parsed_ip));
+                if let Some(reason) = out_of_scope {
+                    results.push((
+                        ip.clone(),
+                        filename,
+                        Err(UnimapError::OutOfScope {
+                            ip: ip.clone(),
+                            reason,
                         }
-                        (ip.clone(), nmap_data)
+                        .into()),
+                    ))
+                } else if config.offline
+                    || (previous_state.done_ips.contains(ip) && Path::new(&filename).is_file())
+                {
+                    let result = scanner.parse(&filename, ip);
+                    results.push((ip.clone(), filename, result))
+                } else {
+                    pending.push((ip.clone(), filename))
+                }
+            }
+            let pending_results = match pending.as_slice() {
+                [] => Vec::new(),
+                [(ip, filename)] => vec![scanner.scan(filename, ip, config)],
+                batch => scanner.scan_batch(batch, config),
+            };
+            results.extend(
+                pending
+                    .into_iter()
+                    .zip(pending_results)
+                    .map(|((ip, filename), result)| (ip, filename, result)),
+            );
+            results
+        })
+        .map(|(ip, filename, result)| {
+            if result.is_ok() {
+                if let Some(file) = done_ips_file.lock().unwrap().as_mut() {
+                    if let Err(e) = writeln!(file, "{}", ip) {
+                        error!("Error saving the scan state. Description: {}", e)
                     }
-                    Err(e) => {
-                        error!("Error scanning the ip {}. Description: {}", &ip, e);
-                        (String::new(), Nmaprun::default())
+                }
+            }
+            if let Ok(scan_data) = &result {
+                on_result(&IpScanResult {
+                    ip: ip.clone(),
+                    hosts: ip_hosts.get(&ip).cloned().unwrap_or_default(),
+                    ports: scan_data.ports.clone(),
+                    host_scripts: scan_data.host_scripts.clone(),
+                })
+            }
+            if result.is_ok()
+                && config.no_keep_nmap_logs
+                && std::fs::remove_file(&filename).is_err()
+            {
+                error!("Error removing filename {}.", &filename)
+            }
+            (ip, result)
+        })
+        .collect::<Vec<_>>()
+    {
+        match result {
+            Ok(scan_data) => {
+                if !scan_data.host_scripts.is_empty() {
+                    host_scripts_data.insert(ip.clone(), scan_data.host_scripts);
+                }
+                nmap_data.insert(ip, scan_data.ports);
+            }
+            Err(e) => {
+                match e.downcast_ref::<UnimapError>() {
+                    Some(UnimapError::NmapNotFound)
+                    | Some(UnimapError::ScannerNotFound { .. })
+                    | Some(UnimapError::InsufficientPrivileges) => return Err(e),
+                    Some(UnimapError::OutOfScope { reason, .. }) => {
+                        excluded.push(Exclusion {
+                            target: ip,
+                            reason: reason.clone(),
+                        });
+                        continue;
                     }
+                    _ => (),
                 }
-            })
-            .collect();
-
-        // Delete the args.logs_dir directory if it's empty
-        if args.no_keep_nmap_logs && std::fs::remove_dir(&args.logs_dir).is_err() {
-            error!("Error removing directory {}.", &args.logs_dir)
+                error!("Error scanning the ip {}. Description: {}", &ip, e);
+                errors.push(ScanError {
+                    target: ip,
+                    description: e.to_string(),
+                })
+            }
         }
+    }
 
-        resolv_data
-            .iter()
-            .map(|(target, resolv_data)| {
-                (
-                    target.clone(),
-                    ResolvData {
-                        ip: resolv_data.ip.clone(),
-                        ports_data: if resolv_data.ip.is_empty() {
-                            resolv_data.ports_data.clone()
-                        } else {
-                            nmap_data
-                                .get_key_value(&resolv_data.ip)
-                                .unwrap()
-                                .1
-                                .host
-                                .clone()
-                                .unwrap_or_default()
-                                .ports
-                                .unwrap_or_default()
-                                .port
+    // Delete the config.logs_dir directory if it's empty
+    if config.no_keep_nmap_logs && std::fs::remove_dir(&config.logs_dir).is_err() {
+        error!("Error removing directory {}.", &config.logs_dir)
+    }
+
+    let hosts = resolv_data
+        .into_iter()
+        .map(|(target, resolv_data)| {
+            let ports_data = resolv_data
+                .ips
+                .iter()
+                .filter_map(|ip| nmap_data.get(ip).map(|ports| (ip.clone(), ports.clone())))
+                .collect();
+            let host_scripts = resolv_data
+                .ips
+                .iter()
+                .filter_map(|ip| {
+                    host_scripts_data
+                        .get(ip)
+                        .map(|scripts| (ip.clone(), scripts.clone()))
+                })
+                .collect();
+            let special_ips = resolv_data
+                .ips
+                .iter()
+                .filter_map(|ip| {
+                    let category = classify_ip(&ip.parse().ok()?);
+                    if category == IpCategory::Global {
+                        None
+                    } else {
+                        Some((ip.clone(), category))
+                    }
+                })
+                .collect();
+            (
+                target,
+                ResolvData {
+                    ports_data,
+                    host_scripts,
+                    special_ips,
+                    ..resolv_data
+                },
+            )
+        })
+        .collect();
+
+    print_exclusions(config, &mut excluded);
+
+    let mut scanned_ips: Vec<String> = nmap_ips.into_iter().collect();
+    scanned_ips.sort();
+
+    Ok(ScanReport {
+        hosts,
+        scanned_ips,
+        excluded,
+        errors,
+        ..Default::default()
+    })
+}
+
+fn resolve_targets(
+    config: &ScanConfig,
+    targets: &HashSet<String>,
+    resolver: &Resolver,
+    consensus_resolvers: &[Resolver],
+    errors: &mut Vec<ScanError>,
+) -> HashMap<String, ResolvData> {
+    let mut resolv_data = HashMap::new();
+    let mut hostnames = Vec::new();
+
+    // IP addresses and CIDR ranges don't need to be resolved
+    for target in targets {
+        if let Ok(ip) = target.parse::<IpAddr>() {
+            resolv_data.insert(
+                target.clone(),
+                ResolvData {
+                    ips: vec![ip.to_string()],
+                    ..Default::default()
+                },
+            );
+        } else if let Ok(cidr) = target.parse::<Cidr>() {
+            if cidr.size() > config.max_cidr_hosts as u128 {
+                let description = format!(
+                    "The range has {} addresses, more than the limit of {}. Skipping it.",
+                    cidr.size(),
+                    config.max_cidr_hosts
+                );
+                if !config.quiet_flag {
+                    error!("Error expanding the range {}. {}", target, description)
+                }
+                errors.push(ScanError {
+                    target: target.clone(),
+                    description,
+                })
+            } else {
+                for ip in cidr.hosts() {
+                    resolv_data.insert(
+                        ip.to_string(),
+                        ResolvData {
+                            ips: vec![ip.to_string()],
+                            ..Default::default()
                         },
-                    },
-                )
-            })
-            .collect()
+                    );
+                }
+            }
+        } else {
+            hostnames.push(target)
+        }
+    }
+
+    resolv_data.par_extend(hostnames.par_iter().map(|target| {
+        // Search domains only apply to names that aren't fully qualified
+        let fqdn_target = if config.search_domains.is_empty() {
+            format!("{}.", target)
+        } else {
+            target.to_string()
+        };
+        let resolv_data = if consensus_resolvers.is_empty() {
+            networking::get_resolv_data(resolver, &fqdn_target, config.servfail_retries)
+        } else {
+            let resolvers: Vec<&Resolver> = consensus_resolvers
+                .choose_multiple(&mut rand::thread_rng(), config.consensus)
+                .collect();
+            let quorum = if config.quorum == 0 {
+                resolvers.len() / 2 + 1
+            } else {
+                config.quorum.min(resolvers.len())
+            };
+            networking::get_consensus_resolv_data(
+                &resolvers,
+                &fqdn_target,
+                quorum,
+                config.servfail_retries,
+            )
+        };
+        (target.to_string(), resolv_data)
+    }));
+
+    resolv_data
+}
+
+/// Probes the parent domain of every hostname for wildcard DNS and flags the
+/// hosts whose IPs are all part of the wildcard answer.
+fn print_exclusions(config: &ScanConfig, excluded: &mut [Exclusion]) {
+    excluded.sort_by(|a, b| a.target.cmp(&b.target));
+    if !config.quiet_flag {
+        for exclusion in excluded.iter() {
+            info!(
+                "{} was not scanned, {}.\n",
+                exclusion.target, exclusion.reason
+            )
+        }
     }
 }
+
+fn flag_wildcards(resolver: &Resolver, resolv_data: &mut HashMap<String, ResolvData>) {
+    let parents: HashSet<String> = resolv_data
+        .keys()
+        .filter(|target| target.parse::<IpAddr>().is_err())
+        .filter_map(|target| logic::return_parent_domain(target))
+        .map(str::to_owned)
+        .collect();
+
+    let wildcard_ips: HashMap<String, HashSet<String>> = parents
+        .into_par_iter()
+        .map(|parent| {
+            let ips = networking::get_wildcard_ips(resolver, &parent);
+            (parent, ips)
+        })
+        .filter(|(_, ips)| !ips.is_empty())
+        .collect();
+
+    for (target, resolv_data) in resolv_data.iter_mut() {
+        if let Some(ips) =
+            logic::return_parent_domain(target).and_then(|parent| wildcard_ips.get(parent))
+        {
+            resolv_data.wildcard =
+                !resolv_data.ips.is_empty() && resolv_data.ips.iter().all(|ip| ips.contains(ip))
+        }
+    }
+}
+
+fn return_resolv_data(hosts: &BTreeMap<String, Vec<String>>) -> HashMap<String, ResolvData> {
+    hosts
+        .iter()
+        .map(|(target, ips)| {
+            (
+                target.to_owned(),
+                ResolvData {
+                    ips: ips.clone(),
+                    ..Default::default()
+                },
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,380 @@
+use {
+    crate::{
+        errors::*,
+        files, masscan, naabu,
+        nmap::{self, Port, Script},
+        structs::ScanConfig,
+        tcp_connect,
+    },
+    log::error,
+    std::{
+        ffi::OsStr,
+        fmt,
+        fs::{self, File},
+        io::ErrorKind,
+        path::Path,
+        process::Command,
+        str::FromStr,
+    },
+};
+
+/// What a scanner found on an IP.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ScanData {
+    /// The open ports, normalized as nmap `Port` records.
+    pub ports: Vec<Port>,
+    /// The results of the scripts that ran against the whole IP instead of a
+    /// single port. Only nmap runs scripts.
+    pub host_scripts: Vec<Script>,
+}
+
+impl From<Vec<Port>> for ScanData {
+    fn from(ports: Vec<Port>) -> ScanData {
+        ScanData {
+            ports,
+            ..Default::default()
+        }
+    }
+}
+
+/// A port scanner backend. Backends write their raw output to a file, which
+/// is reused when resuming or in offline mode, and return what they found on
+/// the scanned IP.
+pub trait PortScanner: Sync {
+    /// Name of the scanner, used in logs and errors.
+    fn name(&self) -> &'static str;
+    /// Extension of the output files, without the dot.
+    fn file_extension(&self) -> &'static str;
+    /// Scans the IP saving the raw output in `filename`.
+    fn scan(&self, filename: &str, ip: &str, config: &ScanConfig) -> Result<ScanData>;
+    /// Scans several IPs, given with the file where the output of each one
+    /// is saved. Scanners that can't scan several IPs at once scan them one
+    /// by one.
+    fn scan_batch(&self, batch: &[(String, String)], config: &ScanConfig) -> Vec<Result<ScanData>> {
+        batch
+            .iter()
+            .map(|(ip, filename)| self.scan(filename, ip, config))
+            .collect()
+    }
+    /// Reads the output of a previous scan of the IP.
+    fn parse(&self, filename: &str, ip: &str) -> Result<ScanData>;
+}
+
+/// The port scanners that unimap can use.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub enum ScannerBackend {
+    #[default]
+    Nmap,
+    Masscan,
+    Naabu,
+    Native,
+}
+
+impl ScannerBackend {
+    pub fn port_scanner(self) -> &'static dyn PortScanner {
+        match self {
+            ScannerBackend::Nmap => &NmapScanner,
+            ScannerBackend::Masscan => &MasscanScanner,
+            ScannerBackend::Naabu => &NaabuScanner,
+            ScannerBackend::Native => &TcpConnectScanner,
+        }
+    }
+}
+
+impl FromStr for ScannerBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<ScannerBackend, String> {
+        match s {
+            "nmap" => Ok(ScannerBackend::Nmap),
+            "masscan" => Ok(ScannerBackend::Masscan),
+            "naabu" => Ok(ScannerBackend::Naabu),
+            "native" => Ok(ScannerBackend::Native),
+            _ => Err(format!("Unknown port scanner {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for ScannerBackend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.port_scanner().name())
+    }
+}
+
+pub struct NmapScanner;
+
+impl PortScanner for NmapScanner {
+    fn name(&self) -> &'static str {
+        "nmap"
+    }
+
+    fn file_extension(&self) -> &'static str {
+        "xml"
+    }
+
+    fn scan(&self, filename: &str, ip: &str, config: &ScanConfig) -> Result<ScanData> {
+        nmap::get_nmap_data(filename, ip, config)
+            .map(|nmap_data| return_nmap_scan_data(&nmap_data, ip))
+    }
+
+    fn scan_batch(&self, batch: &[(String, String)], config: &ScanConfig) -> Vec<Result<ScanData>> {
+        match nmap::get_nmap_batch_data(batch, config) {
+            Ok(nmap_data) => batch
+                .iter()
+                .map(|(ip, _)| Ok(return_nmap_scan_data(&nmap_data, ip)))
+                .collect(),
+            Err(e) => return_batch_errors(batch, &e),
+        }
+    }
+
+    fn parse(&self, filename: &str, ip: &str) -> Result<ScanData> {
+        nmap::parse_nmap_file(filename, ip).map(|nmap_data| return_nmap_scan_data(&nmap_data, ip))
+    }
+}
+
+fn return_nmap_scan_data(nmap_data: &nmap::Nmaprun, ip: &str) -> ScanData {
+    match nmap_data.return_host(ip) {
+        Some(host) => ScanData {
+            ports: host.ports.clone().unwrap_or_default().port,
+            host_scripts: host.hostscript.clone().unwrap_or_default().script,
+        },
+        None => ScanData::default(),
+    }
+}
+
+pub struct MasscanScanner;
+
+impl PortScanner for MasscanScanner {
+    fn name(&self) -> &'static str {
+        "masscan"
+    }
+
+    fn file_extension(&self) -> &'static str {
+        "json"
+    }
+
+    fn scan(&self, filename: &str, ip: &str, config: &ScanConfig) -> Result<ScanData> {
+        masscan::get_masscan_data(filename, ip, config).map(ScanData::from)
+    }
+
+    fn scan_batch(&self, batch: &[(String, String)], config: &ScanConfig) -> Vec<Result<ScanData>> {
+        let result = masscan::get_masscan_batch_data(batch, config);
+        return_batch_results(batch, result, masscan::parse_masscan_file)
+    }
+
+    fn parse(&self, filename: &str, ip: &str) -> Result<ScanData> {
+        masscan::parse_masscan_file(filename, ip).map(ScanData::from)
+    }
+}
+
+pub struct NaabuScanner;
+
+impl PortScanner for NaabuScanner {
+    fn name(&self) -> &'static str {
+        "naabu"
+    }
+
+    fn file_extension(&self) -> &'static str {
+        "jsonl"
+    }
+
+    fn scan(&self, filename: &str, ip: &str, config: &ScanConfig) -> Result<ScanData> {
+        naabu::get_naabu_data(filename, ip, config).map(ScanData::from)
+    }
+
+    fn scan_batch(&self, batch: &[(String, String)], config: &ScanConfig) -> Vec<Result<ScanData>> {
+        let result = naabu::get_naabu_batch_data(batch, config);
+        return_batch_results(batch, result, naabu::parse_naabu_file)
+    }
+
+    fn parse(&self, filename: &str, ip: &str) -> Result<ScanData> {
+        naabu::parse_naabu_file(filename, ip).map(ScanData::from)
+    }
+}
+
+/// Built-in TCP connect scanner, it doesn't need root privileges nor any
+/// external tool.
+pub struct TcpConnectScanner;
+
+impl PortScanner for TcpConnectScanner {
+    fn name(&self) -> &'static str {
+        "native"
+    }
+
+    fn file_extension(&self) -> &'static str {
+        "json"
+    }
+
+    fn scan(&self, filename: &str, ip: &str, config: &ScanConfig) -> Result<ScanData> {
+        tcp_connect::get_tcp_connect_data(filename, ip, config).map(ScanData::from)
+    }
+
+    fn parse(&self, filename: &str, ip: &str) -> Result<ScanData> {
+        tcp_connect::parse_tcp_connect_file(filename, ip).map(ScanData::from)
+    }
+}
+
+/// Runs an external scanner that writes its results to `filename`. Scanners
+/// that don't create the file when nothing is found get an empty one, so the
+/// IP isn't scanned again when resuming.
+pub fn run_scanner<S: AsRef<OsStr>>(
+    scanner: &str,
+    args: &[S],
+    filename: &str,
+    ip: &str,
+) -> Result<()> {
+    match Command::new(scanner).args(args).output() {
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let lowercase_stderr = stderr.to_lowercase();
+            if lowercase_stderr.contains("permission denied")
+                || lowercase_stderr.contains("root privileges")
+            {
+                Err(UnimapError::InsufficientPrivileges.into())
+            } else if output.status.success() {
+                if !Path::new(filename).exists() {
+                    File::create(filename).map_err(|e| UnimapError::ScannerFailed {
+                        scanner: scanner.to_string(),
+                        ip: ip.to_string(),
+                        description: e.to_string(),
+                    })?;
+                }
+                Ok(())
+            } else {
+                Err(UnimapError::ScannerFailed {
+                    scanner: scanner.to_string(),
+                    ip: ip.to_string(),
+                    description: stderr.trim().to_string(),
+                }
+                .into())
+            }
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => Err(UnimapError::ScannerNotFound {
+            scanner: scanner.to_string(),
+        }
+        .into()),
+        Err(e) => Err(UnimapError::ScannerFailed {
+            scanner: scanner.to_string(),
+            ip: ip.to_string(),
+            description: e.to_string(),
+        }
+        .into()),
+    }
+}
+
+/// Reads the output file of an external scanner.
+pub fn read_scanner_file(scanner: &str, filename: &str, ip: &str) -> Result<String> {
+    if !Path::new(filename).is_file() {
+        return Err(UnimapError::ScannerOutputNotFound {
+            scanner: scanner.to_string(),
+            ip: ip.to_string(),
+            file: filename.to_string(),
+        }
+        .into());
+    }
+    fs::read_to_string(filename).map_err(|e| {
+        UnimapError::FileOpen {
+            file: filename.to_string(),
+            description: e.to_string(),
+        }
+        .into()
+    })
+}
+
+/// Writes the IPs of a batch to a list file in the logs directory, for the
+/// scanners that read their targets from a file. Returns the list filename
+/// and the filename where the output of the whole batch is saved.
+pub fn write_batch_list(
+    scanner: &str,
+    batch: &[(String, String)],
+    config: &ScanConfig,
+) -> Result<(String, String)> {
+    let first_ip = batch.first().map(|(ip, _)| ip.as_str()).unwrap_or_default();
+    let batch_name = format!("batch-{}", first_ip);
+    let list_filename = files::return_scan_filename(&config.logs_dir, &batch_name, "txt");
+    let output_filename = files::return_scan_filename(
+        &config.logs_dir,
+        &batch_name,
+        config.scanner.port_scanner().file_extension(),
+    );
+    let ips: Vec<&str> = batch.iter().map(|(ip, _)| ip.as_str()).collect();
+    fs::write(&list_filename, ips.join("\n") + "\n").map_err(|e| UnimapError::ScannerFailed {
+        scanner: scanner.to_string(),
+        ip: return_batch_label(batch),
+        description: e.to_string(),
+    })?;
+    Ok((list_filename, output_filename))
+}
+
+/// Gives every IP of a batch its own copy of the batch output, so resuming
+/// and offline mode work as with one scan per IP. The parsers only read the
+/// records of the requested IP.
+pub fn split_batch_output(
+    scanner: &str,
+    batch: &[(String, String)],
+    output_filename: &str,
+) -> Result<()> {
+    for (ip, filename) in batch {
+        let _ = fs::remove_file(filename);
+        if fs::hard_link(output_filename, filename).is_err() {
+            fs::copy(output_filename, filename).map_err(|e| UnimapError::ScannerFailed {
+                scanner: scanner.to_string(),
+                ip: ip.clone(),
+                description: e.to_string(),
+            })?;
+        }
+    }
+    if fs::remove_file(output_filename).is_err() {
+        error!("Error removing filename {}.", output_filename)
+    }
+    Ok(())
+}
+
+/// Name of a batch used in logs and errors.
+pub fn return_batch_label(batch: &[(String, String)]) -> String {
+    format!(
+        "{} and {} more IPs",
+        batch.first().map(|(ip, _)| ip.as_str()).unwrap_or_default(),
+        batch.len().saturating_sub(1)
+    )
+}
+
+/// Packets per second of every scanner process. `--min-rate` is the rate of
+/// the whole run for masscan and naabu, so it's shared by the processes that
+/// run in parallel.
+pub fn return_process_rate(config: &ScanConfig) -> String {
+    match config.min_rate.trim().parse::<u64>() {
+        Ok(rate) => (rate / config.threads.max(1) as u64).max(1).to_string(),
+        Err(_) => config.min_rate.clone(),
+    }
+}
+
+/// Turns the result of a batch scan into a result per IP, read from the
+/// output file of each one.
+pub fn return_batch_results<F>(
+    batch: &[(String, String)],
+    result: Result<()>,
+    parse: F,
+) -> Vec<Result<ScanData>>
+where
+    F: Fn(&str, &str) -> Result<Vec<Port>>,
+{
+    match result {
+        Ok(()) => batch
+            .iter()
+            .map(|(ip, filename)| parse(filename, ip).map(ScanData::from))
+            .collect(),
+        Err(e) => return_batch_errors(batch, &e),
+    }
+}
+
+/// Gives every IP of a failed batch scan the error of the whole batch.
+fn return_batch_errors(batch: &[(String, String)], e: &Error) -> Vec<Result<ScanData>> {
+    batch
+        .iter()
+        .map(|_| match e.downcast_ref::<UnimapError>() {
+            Some(e) => Err(e.clone().into()),
+            None => Err(failure::err_msg(e.to_string())),
+        })
+        .collect()
+}
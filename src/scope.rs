@@ -0,0 +1,139 @@
+use {
+    crate::{addresses::Cidr, errors::*},
+    std::{fmt, fs, net::IpAddr},
+};
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum ScopeRule {
+    Host(String),
+    /// `*.example.com`, stored as `.example.com`. It matches the subdomains
+    /// only, not `example.com` itself.
+    Wildcard(String),
+    Ip(IpAddr),
+    Cidr(Cidr),
+}
+
+impl ScopeRule {
+    pub fn parse(rule: &str) -> ScopeRule {
+        let rule = rule.trim().to_lowercase();
+        if let Ok(ip) = rule.parse::<IpAddr>() {
+            ScopeRule::Ip(ip)
+        } else if let Ok(cidr) = rule.parse::<Cidr>() {
+            ScopeRule::Cidr(cidr)
+        } else if let Some(suffix) = rule.strip_prefix("*.") {
+            ScopeRule::Wildcard(format!(".{}", suffix))
+        } else {
+            ScopeRule::Host(rule)
+        }
+    }
+
+    fn is_host_rule(&self) -> bool {
+        matches!(self, ScopeRule::Host(_) | ScopeRule::Wildcard(_))
+    }
+
+    fn matches_host(&self, host: &str) -> bool {
+        match self {
+            ScopeRule::Host(rule) => rule == host,
+            ScopeRule::Wildcard(suffix) => host.ends_with(suffix.as_str()),
+            _ => false,
+        }
+    }
+
+    fn matches_ip(&self, ip: &IpAddr) -> bool {
+        match self {
+            ScopeRule::Ip(rule) => rule == ip,
+            ScopeRule::Cidr(cidr) => cidr.contains(ip),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for ScopeRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScopeRule::Host(host) => write!(f, "{}", host),
+            ScopeRule::Wildcard(suffix) => write!(f, "*{}", suffix),
+            ScopeRule::Ip(ip) => write!(f, "{}", ip),
+            ScopeRule::Cidr(cidr) => write!(f, "{}", cidr),
+        }
+    }
+}
+
+/// Allowlist and exclusion rules checked before scanning anything. An empty
+/// allowlist allows everything that isn't excluded.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Scope {
+    pub allowlist: Vec<ScopeRule>,
+    pub exclusions: Vec<ScopeRule>,
+}
+
+impl Scope {
+    pub fn from_files(scope_files: &[String], exclude_files: &[String]) -> Result<Scope> {
+        Ok(Scope {
+            allowlist: read_rules(scope_files)?,
+            exclusions: read_rules(exclude_files)?,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.allowlist.is_empty() && self.exclusions.is_empty()
+    }
+
+    /// Returns the reason why the host is out of scope, if it is.
+    pub fn check_host(&self, host: &str) -> Option<String> {
+        let host = host.to_lowercase();
+        if let Some(rule) = self.exclusions.iter().find(|r| r.matches_host(&host)) {
+            return Some(format!("excluded by the rule {}", rule));
+        }
+        if self.allowlist.iter().any(ScopeRule::is_host_rule)
+            && !self.allowlist.iter().any(|r| r.matches_host(&host))
+        {
+            return Some("the host doesn't match any rule of the scope".to_string());
+        }
+        None
+    }
+
+    /// Returns the reason why the IP is out of scope, if it is. The
+    /// allowlist only restricts IPs when it contains IP or CIDR rules.
+    pub fn check_ip(&self, ip: &IpAddr) -> Option<String> {
+        if let Some(rule) = self.exclusions.iter().find(|r| r.matches_ip(ip)) {
+            return Some(format!("excluded by the rule {}", rule));
+        }
+        if self.allowlist.iter().any(|r| !r.is_host_rule())
+            && !self.allowlist.iter().any(|r| r.matches_ip(ip))
+        {
+            return Some("the IP doesn't match any rule of the scope".to_string());
+        }
+        None
+    }
+
+    /// Like `check_ip`, for IPs given as targets instead of resolved from an
+    /// allowed host. An allowlist without IP or CIDR rules allows none of
+    /// them.
+    pub fn check_ip_target(&self, ip: &IpAddr) -> Option<String> {
+        if let Some(reason) = self.check_ip(ip) {
+            return Some(reason);
+        }
+        if !self.allowlist.is_empty() && self.allowlist.iter().all(ScopeRule::is_host_rule) {
+            return Some("the scope has no IP or CIDR rules".to_string());
+        }
+        None
+    }
+}
+
+fn read_rules(files: &[String]) -> Result<Vec<ScopeRule>> {
+    let mut rules = Vec::new();
+    for file in files {
+        let data = fs::read_to_string(file).map_err(|e| UnimapError::FileOpen {
+            file: file.clone(),
+            description: e.to_string(),
+        })?;
+        rules.extend(
+            data.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(ScopeRule::parse),
+        );
+    }
+    Ok(rules)
+}
@@ -1,6 +1,17 @@
 use {
-    crate::nmap::Port,
-    std::{collections::HashSet, time::Instant},
+    crate::{
+        addresses::IpCategory,
+        defaults,
+        nameservers::Nameserver,
+        nmap::{Port, Script},
+        scanners::ScannerBackend,
+        scope::Scope,
+    },
+    std::{
+        collections::{BTreeMap, BTreeSet, HashSet},
+        fmt,
+        time::{Duration, Instant},
+    },
 };
 
 #[derive(Clone, Debug)]
@@ -22,23 +33,450 @@ pub struct Args {
     pub raw_output: bool,
     pub fast_scan: bool,
     pub url_output: bool,
+    pub json_output: bool,
+    pub jsonl_output: bool,
     pub from_stdin: bool,
+    pub scanner: ScannerBackend,
+    pub batch_size: usize,
+    pub connect_timeout: u64,
+    pub scripts: String,
+    pub script_args: String,
+    pub ipv6: bool,
+    pub resume: bool,
+    pub offline: bool,
+    pub hosts_map: String,
+    pub max_cidr_hosts: usize,
     pub files: Vec<String>,
+    pub scope_files: Vec<String>,
+    pub exclude_files: Vec<String>,
+    pub include_internal: bool,
+    pub drop_wildcards: bool,
+    pub skip_resolver_check: bool,
+    pub max_resolver_latency: u64,
+    pub consensus: usize,
+    pub quorum: usize,
+    pub dns_timeout: u64,
+    pub dns_attempts: usize,
+    pub dns_concurrent_reqs: usize,
+    pub servfail_retries: usize,
+    pub system_resolvers: bool,
     pub resolvers: Vec<String>,
     pub targets: HashSet<String>,
     pub time_wasted: Instant,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+/// Why a host didn't resolve to any IP.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ResolveFailure {
+    Nxdomain,
+    Servfail,
+    Refused,
+    Timeout,
+    /// The name exists but has no A (or AAAA) records.
+    NoAddress,
+    Other,
+}
+
+impl fmt::Display for ResolveFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ResolveFailure::Nxdomain => "NXDOMAIN",
+            ResolveFailure::Servfail => "SERVFAIL",
+            ResolveFailure::Refused => "REFUSED",
+            ResolveFailure::Timeout => "timeout",
+            ResolveFailure::NoAddress => "no A record",
+            ResolveFailure::Other => "error",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct ResolvData {
+    pub ips: Vec<String>,
+    /// CNAME targets followed from the host to its IPs, in order.
+    pub cname_chain: Vec<String>,
+    /// The CNAME chain doesn't end in an address, a possible takeover.
+    pub dangling_cname: bool,
+    /// IPs returned by some of the resolvers but not confirmed by the quorum
+    /// in consensus mode, they aren't scanned.
+    pub unconfirmed_ips: Vec<String>,
+    pub ports_data: BTreeMap<String, Vec<Port>>,
+    /// Output of the nmap scripts that ran against the whole IP, for the IPs
+    /// that have any.
+    pub host_scripts: BTreeMap<String, Vec<Script>>,
+    /// Category of the IPs that aren't globally reachable.
+    pub special_ips: BTreeMap<String, IpCategory>,
+    /// The host only resolves to the wildcard DNS answer of its parent domain.
+    pub wildcard: bool,
+    /// Why the host didn't resolve, when it has no IPs.
+    pub failure: Option<ResolveFailure>,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ScanError {
+    pub target: String,
+    pub description: String,
+}
+
+/// Result of the health check of a resolver, `problem` is set when the
+/// resolver was dropped.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ResolverCheck {
+    pub resolver: String,
+    pub latency_ms: Option<u64>,
+    pub problem: Option<String>,
+}
+
+impl ResolverCheck {
+    pub fn is_healthy(&self) -> bool {
+        self.problem.is_none()
+    }
+}
+
+/// A host or IP that was left out of the scan and why.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Exclusion {
+    pub target: String,
+    pub reason: String,
+}
+
+/// A single host, IP and port combination, used for the NDJSON output.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PortRecord {
+    pub host: String,
+    pub ip: String,
+    pub port: Port,
+}
+
+/// Results of a single IP, emitted as soon as its scan finishes.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct IpScanResult {
     pub ip: String,
-    pub ports_data: Vec<Port>,
+    pub hosts: Vec<String>,
+    pub ports: Vec<Port>,
+    pub host_scripts: Vec<Script>,
 }
-impl ResolvData {
-    pub fn default() -> ResolvData {
-        ResolvData {
-            ip: String::new(),
-            ports_data: Vec::new(),
+
+/// Progress of a run, saved in the logs directory so an interrupted scan can
+/// be resumed reusing the nmap XML files of the IPs that were already done.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ScanState {
+    pub hosts: BTreeMap<String, Vec<String>>,
+    pub done_ips: BTreeSet<String>,
+    #[serde(default)]
+    pub wildcards: BTreeSet<String>,
+    #[serde(default)]
+    pub cname_chains: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    pub dangling_cnames: BTreeSet<String>,
+    #[serde(default)]
+    pub failures: BTreeMap<String, ResolveFailure>,
+    #[serde(default)]
+    pub unconfirmed_ips: BTreeMap<String, Vec<String>>,
+}
+
+/// Results of a whole run: every target with its IPs and the open ports found
+/// on each of them, the unique IPs that were scanned and the errors found.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ScanReport {
+    pub hosts: BTreeMap<String, ResolvData>,
+    pub scanned_ips: Vec<String>,
+    pub excluded: Vec<Exclusion>,
+    pub errors: Vec<ScanError>,
+    pub started_at: String,
+    pub finished_at: String,
+    pub elapsed_secs: u64,
+}
+
+/// Options used by the resolution and scanning engine. Build it with
+/// `ScanConfig::builder()` or from the command line arguments with
+/// `args::return_scan_config`.
+#[derive(Clone, Debug)]
+pub struct ScanConfig {
+    pub resolvers: Vec<Nameserver>,
+    pub threads: usize,
+    pub logs_dir: String,
+    pub ports: String,
+    pub min_rate: String,
+    pub fast_scan: bool,
+    pub no_keep_nmap_logs: bool,
+    pub scanner: ScannerBackend,
+    pub batch_size: usize,
+    pub connect_timeout: Duration,
+    pub scripts: String,
+    pub script_args: String,
+    pub ipv6: bool,
+    pub resume: bool,
+    pub offline: bool,
+    pub hosts_map: Option<String>,
+    pub max_cidr_hosts: usize,
+    pub scope: Scope,
+    pub include_internal: bool,
+    pub drop_wildcards: bool,
+    pub check_resolvers: bool,
+    pub health_check_domain: String,
+    pub max_resolver_latency: Duration,
+    pub consensus: usize,
+    pub quorum: usize,
+    pub dns_timeout: Duration,
+    pub dns_attempts: usize,
+    pub dns_concurrent_reqs: usize,
+    pub servfail_retries: usize,
+    pub search_domains: Vec<String>,
+    pub ndots: usize,
+    pub quiet_flag: bool,
+}
+
+impl ScanConfig {
+    pub fn builder() -> ScanConfigBuilder {
+        ScanConfigBuilder::default()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ScanConfigBuilder {
+    config: ScanConfig,
+}
+
+impl Default for ScanConfigBuilder {
+    fn default() -> ScanConfigBuilder {
+        ScanConfigBuilder {
+            config: ScanConfig {
+                resolvers: defaults::ipv4_resolvers()
+                    .iter()
+                    .filter_map(|r| r.parse().ok())
+                    .collect(),
+                threads: 50,
+                logs_dir: String::from("unimap_logs"),
+                ports: String::new(),
+                min_rate: String::new(),
+                fast_scan: false,
+                no_keep_nmap_logs: false,
+                scanner: ScannerBackend::Nmap,
+                batch_size: 1,
+                connect_timeout: Duration::from_secs(1),
+                scripts: String::new(),
+                script_args: String::new(),
+                ipv6: false,
+                resume: false,
+                offline: false,
+                hosts_map: None,
+                max_cidr_hosts: 65536,
+                scope: Scope::default(),
+                include_internal: false,
+                drop_wildcards: false,
+                check_resolvers: true,
+                health_check_domain: defaults::HEALTH_CHECK_DOMAIN.to_string(),
+                max_resolver_latency: Duration::from_millis(500),
+                consensus: 0,
+                quorum: 0,
+                dns_timeout: Duration::from_secs(1),
+                dns_attempts: 2,
+                dns_concurrent_reqs: 1,
+                servfail_retries: 0,
+                search_domains: Vec::new(),
+                ndots: 1,
+                quiet_flag: false,
+            },
         }
     }
 }
+
+impl ScanConfigBuilder {
+    pub fn resolvers(mut self, resolvers: Vec<Nameserver>) -> ScanConfigBuilder {
+        self.config.resolvers = resolvers;
+        self
+    }
+
+    pub fn threads(mut self, threads: usize) -> ScanConfigBuilder {
+        self.config.threads = threads;
+        self
+    }
+
+    pub fn logs_dir(mut self, logs_dir: &str) -> ScanConfigBuilder {
+        self.config.logs_dir = logs_dir.to_string();
+        self
+    }
+
+    pub fn ports(mut self, ports: &str) -> ScanConfigBuilder {
+        self.config.ports = ports.to_string();
+        self
+    }
+
+    pub fn min_rate(mut self, min_rate: &str) -> ScanConfigBuilder {
+        self.config.min_rate = min_rate.to_string();
+        self
+    }
+
+    pub fn fast_scan(mut self, fast_scan: bool) -> ScanConfigBuilder {
+        self.config.fast_scan = fast_scan;
+        self
+    }
+
+    pub fn no_keep_nmap_logs(mut self, no_keep_nmap_logs: bool) -> ScanConfigBuilder {
+        self.config.no_keep_nmap_logs = no_keep_nmap_logs;
+        self
+    }
+
+    /// Port scanner used for the unique IPs, nmap by default.
+    pub fn scanner(mut self, scanner: ScannerBackend) -> ScanConfigBuilder {
+        self.config.scanner = scanner;
+        self
+    }
+
+    /// Number of IPs passed to every nmap, masscan or naabu run, one by
+    /// default. The native scanner still scans the IPs one by one.
+    pub fn batch_size(mut self, batch_size: usize) -> ScanConfigBuilder {
+        self.config.batch_size = batch_size;
+        self
+    }
+
+    /// How long the TCP connect scanner waits for every connection.
+    pub fn connect_timeout(mut self, connect_timeout: Duration) -> ScanConfigBuilder {
+        self.config.connect_timeout = connect_timeout;
+        self
+    }
+
+    /// NSE scripts passed to nmap with --script, none by default.
+    pub fn scripts(mut self, scripts: &str) -> ScanConfigBuilder {
+        self.config.scripts = scripts.to_string();
+        self
+    }
+
+    /// Arguments of the NSE scripts, passed to nmap with --script-args.
+    pub fn script_args(mut self, script_args: &str) -> ScanConfigBuilder {
+        self.config.script_args = script_args.to_string();
+        self
+    }
+
+    pub fn ipv6(mut self, ipv6: bool) -> ScanConfigBuilder {
+        self.config.ipv6 = ipv6;
+        self
+    }
+
+    /// Continue the run saved in `logs_dir` instead of resolving new targets.
+    pub fn resume(mut self, resume: bool) -> ScanConfigBuilder {
+        self.config.resume = resume;
+        self
+    }
+
+    /// Build the report from the nmap XML files already present in
+    /// `logs_dir` instead of running nmap.
+    pub fn offline(mut self, offline: bool) -> ScanConfigBuilder {
+        self.config.offline = offline;
+        self
+    }
+
+    /// Path to a file with `HOST,IP` lines used instead of resolving the targets.
+    pub fn hosts_map(mut self, hosts_map: &str) -> ScanConfigBuilder {
+        self.config.hosts_map = Some(hosts_map.to_string());
+        self
+    }
+
+    /// CIDR targets with more addresses than this are skipped.
+    pub fn max_cidr_hosts(mut self, max_cidr_hosts: usize) -> ScanConfigBuilder {
+        self.config.max_cidr_hosts = max_cidr_hosts;
+        self
+    }
+
+    /// Hosts and IPs out of the scope are never scanned.
+    pub fn scope(mut self, scope: Scope) -> ScanConfigBuilder {
+        self.config.scope = scope;
+        self
+    }
+
+    /// Also scan private, shared (CGNAT), link-local and unique local
+    /// addresses, useful for internal assessments.
+    pub fn include_internal(mut self, include_internal: bool) -> ScanConfigBuilder {
+        self.config.include_internal = include_internal;
+        self
+    }
+
+    /// Leave out of the report the hosts that only resolve to the wildcard
+    /// DNS answer of their parent domain instead of just flagging them.
+    pub fn drop_wildcards(mut self, drop_wildcards: bool) -> ScanConfigBuilder {
+        self.config.drop_wildcards = drop_wildcards;
+        self
+    }
+
+    /// Query every resolver before the run and drop the ones that fail,
+    /// hijack NXDOMAIN answers or are slower than `max_resolver_latency`.
+    pub fn check_resolvers(mut self, check_resolvers: bool) -> ScanConfigBuilder {
+        self.config.check_resolvers = check_resolvers;
+        self
+    }
+
+    /// Name that every resolver must be able to resolve in the health check.
+    pub fn health_check_domain(mut self, domain: &str) -> ScanConfigBuilder {
+        self.config.health_check_domain = domain.to_string();
+        self
+    }
+
+    pub fn max_resolver_latency(mut self, max_resolver_latency: Duration) -> ScanConfigBuilder {
+        self.config.max_resolver_latency = max_resolver_latency;
+        self
+    }
+
+    /// Resolve every hostname through this many independent resolvers and
+    /// only keep the answers confirmed by the quorum. Disabled below 2.
+    pub fn consensus(mut self, consensus: usize) -> ScanConfigBuilder {
+        self.config.consensus = consensus;
+        self
+    }
+
+    /// Resolvers that must agree on an IP in consensus mode, the majority
+    /// when it's 0.
+    pub fn quorum(mut self, quorum: usize) -> ScanConfigBuilder {
+        self.config.quorum = quorum;
+        self
+    }
+
+    /// Time to wait for every DNS query.
+    pub fn dns_timeout(mut self, dns_timeout: Duration) -> ScanConfigBuilder {
+        self.config.dns_timeout = dns_timeout;
+        self
+    }
+
+    /// Times a DNS query is sent before giving up on timeouts and
+    /// connection errors.
+    pub fn dns_attempts(mut self, dns_attempts: usize) -> ScanConfigBuilder {
+        self.config.dns_attempts = dns_attempts;
+        self
+    }
+
+    /// Nameservers queried at the same time for every lookup.
+    pub fn dns_concurrent_reqs(mut self, dns_concurrent_reqs: usize) -> ScanConfigBuilder {
+        self.config.dns_concurrent_reqs = dns_concurrent_reqs;
+        self
+    }
+
+    /// Times a lookup answered with SERVFAIL is retried.
+    pub fn servfail_retries(mut self, servfail_retries: usize) -> ScanConfigBuilder {
+        self.config.servfail_retries = servfail_retries;
+        self
+    }
+
+    /// Domains appended to the hostnames with less than `ndots` dots, like
+    /// the `search` option of resolv.conf. Hostnames are resolved as fully
+    /// qualified names when it's empty.
+    pub fn search_domains(mut self, search_domains: Vec<String>) -> ScanConfigBuilder {
+        self.config.search_domains = search_domains;
+        self
+    }
+
+    pub fn ndots(mut self, ndots: usize) -> ScanConfigBuilder {
+        self.config.ndots = ndots;
+        self
+    }
+
+    pub fn quiet(mut self, quiet: bool) -> ScanConfigBuilder {
+        self.config.quiet_flag = quiet;
+        self
+    }
+
+    pub fn build(self) -> ScanConfig {
+        self.config
+    }
+}
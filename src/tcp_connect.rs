@@ -0,0 +1,144 @@
+use {
+    crate::{
+        defaults,
+        errors::*,
+        nmap::{Port, State},
+        scanners::read_scanner_file,
+        structs::ScanConfig,
+    },
+    futures_util::stream::{self, StreamExt},
+    std::{
+        collections::BTreeSet,
+        io::{self, ErrorKind},
+        net::{IpAddr, SocketAddr},
+        time::Duration,
+    },
+    tokio::{net::TcpStream, runtime, time},
+};
+
+pub fn get_tcp_connect_data(filename: &str, host: &str, config: &ScanConfig) -> Result<Vec<Port>> {
+    let scanner_failed = |description: String| UnimapError::ScannerFailed {
+        scanner: "native".to_string(),
+        ip: host.to_string(),
+        description,
+    };
+    let ip: IpAddr = host
+        .parse()
+        .map_err(|_| scanner_failed(format!("{} is not an IP address", host)))?;
+    let ports = return_port_list(&config.ports)?;
+    let concurrency = (defaults::CONNECT_MAX_SOCKETS / config.threads.max(1)).max(1);
+
+    let runtime = runtime::Builder::new_current_thread()
+        .enable_io()
+        .enable_time()
+        .build()
+        .map_err(|e| scanner_failed(e.to_string()))?;
+    let results: Vec<io::Result<Option<u16>>> = runtime.block_on(
+        stream::iter(ports)
+            .map(|port| check_port(SocketAddr::new(ip, port), config.connect_timeout))
+            .buffer_unordered(concurrency)
+            .collect(),
+    );
+
+    let mut open_ports = BTreeSet::new();
+    for result in results {
+        if let Some(port) = result.map_err(|e| scanner_failed(e.to_string()))? {
+            open_ports.insert(port);
+        }
+    }
+    // Same records as a nmap TCP connect scan, without service detection
+    let ports: Vec<Port> = open_ports
+        .into_iter()
+        .map(|port| Port {
+            protocol: "tcp".to_string(),
+            portid: port.to_string(),
+            state: State {
+                state: "open".to_string(),
+                reason: "syn-ack".to_string(),
+                reason_ttl: "0".to_string(),
+                ..Default::default()
+            },
+            service: None,
+            ..Default::default()
+        })
+        .collect();
+
+    let data = serde_json::to_string_pretty(&ports).map_err(|e| scanner_failed(e.to_string()))?;
+    std::fs::write(filename, data).map_err(|e| scanner_failed(e.to_string()))?;
+    Ok(ports)
+}
+
+pub fn parse_tcp_connect_file(filename: &str, host: &str) -> Result<Vec<Port>> {
+    let data = read_scanner_file("native", filename, host)?;
+    serde_json::from_str(&data).map_err(|e| {
+        UnimapError::ScannerOutputParse {
+            scanner: "native".to_string(),
+            ip: host.to_string(),
+            description: e.to_string(),
+        }
+        .into()
+    })
+}
+
+// Refused and unanswered connections are closed or filtered ports, anything
+// else means that the host can't be scanned
+async fn check_port(socket_addr: SocketAddr, timeout: Duration) -> io::Result<Option<u16>> {
+    match time::timeout(timeout, TcpStream::connect(socket_addr)).await {
+        Ok(Ok(_)) => Ok(Some(socket_addr.port())),
+        Ok(Err(e))
+            if matches!(
+                e.kind(),
+                ErrorKind::ConnectionRefused | ErrorKind::ConnectionReset | ErrorKind::TimedOut
+            ) =>
+        {
+            Ok(None)
+        }
+        Ok(Err(e)) => Err(e),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Expands a list of ports and ranges in the nmap syntax, like
+/// `22, 80, 443, 1000-5000`, to the TCP ports it contains. The 100 most
+/// common TCP ports are returned for an empty list.
+pub fn return_port_list(ports: &str) -> Result<Vec<u16>> {
+    if ports.trim().is_empty() {
+        return Ok(defaults::TOP_TCP_PORTS.to_vec());
+    }
+    let invalid_ports = |description: String| UnimapError::InvalidPorts {
+        ports: ports.to_string(),
+        description,
+    };
+    let parse_port = |port: &str, default: u16| -> Result<u16> {
+        match port.trim() {
+            "" => Ok(default),
+            port => match port.parse::<u16>() {
+                Ok(port) if port > 0 => Ok(port),
+                _ => Err(invalid_ports(format!("{} is not a valid port", port)).into()),
+            },
+        }
+    };
+
+    let mut port_list = BTreeSet::new();
+    for item in ports
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+    {
+        let item = item.strip_prefix("T:").unwrap_or(item);
+        if item.contains(':') {
+            return Err(
+                invalid_ports(format!("only TCP ports can be scanned, found {}", item)).into(),
+            );
+        }
+        let (first, last) = match item.split_once('-') {
+            Some((first, last)) => (parse_port(first, 1)?, parse_port(last, u16::MAX)?),
+            None => (parse_port(item, 1)?, parse_port(item, 1)?),
+        };
+        if first > last {
+            return Err(invalid_ports(format!("the range {} is reversed", item)).into());
+        }
+        port_list.extend(first..=last)
+    }
+    Ok(port_list.into_iter().collect())
+}
@@ -0,0 +1,193 @@
+use {
+    std::net::IpAddr,
+    unimap::addresses::{classify_ip, Cidr, IpCategory},
+};
+
+fn return_cidr(cidr: &str) -> Cidr {
+    cidr.parse().unwrap()
+}
+
+fn return_ip(ip: &str) -> IpAddr {
+    ip.parse().unwrap()
+}
+
+fn return_hosts(cidr: &str) -> Vec<String> {
+    return_cidr(cidr).hosts().map(|ip| ip.to_string()).collect()
+}
+
+#[test]
+fn host_bits_are_masked_out() {
+    let cidr = return_cidr("192.0.2.77/24");
+    assert_eq!(cidr.network(), return_ip("192.0.2.0"));
+    assert_eq!(cidr.to_string(), "192.0.2.0/24");
+    assert_eq!(
+        return_cidr("2001:db8::ffff/127").to_string(),
+        "2001:db8::fffe/127"
+    );
+    assert_eq!(return_cidr("203.0.113.9/0").to_string(), "0.0.0.0/0");
+    assert_eq!(return_cidr("2001:db8::1/0").to_string(), "::/0");
+}
+
+#[test]
+fn invalid_cidrs_are_rejected() {
+    for cidr in &[
+        "192.0.2.0",
+        "192.0.2.0/33",
+        "2001:db8::/129",
+        "192.0.2.0/",
+        "192.0.2.0/-1",
+        "example.com/24",
+    ] {
+        assert!(cidr.parse::<Cidr>().is_err(), "{} was accepted", cidr);
+    }
+}
+
+#[test]
+fn ipv4_networks_skip_the_network_and_broadcast_addresses() {
+    assert_eq!(return_cidr("192.0.2.0/24").size(), 254);
+    assert_eq!(return_hosts("192.0.2.0/30"), vec!["192.0.2.1", "192.0.2.2"]);
+    assert_eq!(return_cidr("192.0.2.0/30").size(), 2);
+}
+
+#[test]
+fn ipv4_edge_prefixes() {
+    // Point-to-point links use both addresses of a /31
+    assert_eq!(return_cidr("192.0.2.0/31").size(), 2);
+    assert_eq!(return_hosts("192.0.2.0/31"), vec!["192.0.2.0", "192.0.2.1"]);
+    assert_eq!(return_cidr("192.0.2.7/32").size(), 1);
+    assert_eq!(return_hosts("192.0.2.7/32"), vec!["192.0.2.7"]);
+
+    let all = return_cidr("0.0.0.0/0");
+    assert_eq!(all.size(), u128::from(u32::MAX) - 1);
+    assert_eq!(all.hosts().next(), Some(return_ip("0.0.0.1")));
+}
+
+#[test]
+fn ipv6_edge_prefixes() {
+    // IPv6 has no broadcast, every address is a host
+    assert_eq!(return_cidr("2001:db8::/127").size(), 2);
+    assert_eq!(
+        return_hosts("2001:db8::/127"),
+        vec!["2001:db8::", "2001:db8::1"]
+    );
+    assert_eq!(return_cidr("2001:db8::1/128").size(), 1);
+    assert_eq!(return_hosts("2001:db8::1/128"), vec!["2001:db8::1"]);
+    assert_eq!(return_cidr("2001:db8::/120").size(), 256);
+
+    // 2^128 doesn't fit, the size saturates
+    let all = return_cidr("::/0");
+    assert_eq!(all.size(), u128::MAX);
+    assert_eq!(all.hosts().next(), Some(return_ip("::")));
+}
+
+#[test]
+fn contains_checks_the_prefix_and_the_family() {
+    let cidr = return_cidr("198.51.100.0/25");
+    assert!(cidr.contains(&return_ip("198.51.100.0")));
+    assert!(cidr.contains(&return_ip("198.51.100.127")));
+    assert!(!cidr.contains(&return_ip("198.51.100.128")));
+    assert!(!cidr.contains(&return_ip("::ffff:198.51.100.1")));
+
+    assert!(return_cidr("192.0.2.7/32").contains(&return_ip("192.0.2.7")));
+    assert!(!return_cidr("192.0.2.7/32").contains(&return_ip("192.0.2.6")));
+    assert!(return_cidr("0.0.0.0/0").contains(&return_ip("255.255.255.255")));
+    assert!(!return_cidr("0.0.0.0/0").contains(&return_ip("::1")));
+    assert!(return_cidr("::/0").contains(&return_ip("ffff::1")));
+    assert!(!return_cidr("::/0").contains(&return_ip("127.0.0.1")));
+    assert!(return_cidr("fe80::/10").contains(&return_ip("febf::1")));
+    assert!(!return_cidr("fe80::/10").contains(&return_ip("fec0::1")));
+}
+
+#[test]
+fn every_ip_category_is_classified() {
+    let cases = [
+        ("93.184.216.34", IpCategory::Global),
+        ("2606:2800:220:1::1", IpCategory::Global),
+        ("0.0.0.0", IpCategory::Unspecified),
+        ("0.1.2.3", IpCategory::Unspecified),
+        ("::", IpCategory::Unspecified),
+        ("10.1.2.3", IpCategory::Private),
+        ("172.16.0.1", IpCategory::Private),
+        ("172.31.255.255", IpCategory::Private),
+        ("192.168.1.1", IpCategory::Private),
+        ("100.64.0.1", IpCategory::SharedAddress),
+        ("100.127.255.254", IpCategory::SharedAddress),
+        ("127.0.0.1", IpCategory::Loopback),
+        ("::1", IpCategory::Loopback),
+        ("169.254.1.1", IpCategory::LinkLocal),
+        ("fe80::1", IpCategory::LinkLocal),
+        ("fc00::1", IpCategory::UniqueLocal),
+        ("fd12:3456::1", IpCategory::UniqueLocal),
+        ("192.0.2.1", IpCategory::Documentation),
+        ("198.51.100.1", IpCategory::Documentation),
+        ("203.0.113.1", IpCategory::Documentation),
+        ("2001:db8::1", IpCategory::Documentation),
+        ("198.18.0.1", IpCategory::Benchmarking),
+        ("198.19.255.255", IpCategory::Benchmarking),
+        ("2001:2::1", IpCategory::Benchmarking),
+        ("224.0.0.1", IpCategory::Multicast),
+        ("239.255.255.250", IpCategory::Multicast),
+        ("ff02::1", IpCategory::Multicast),
+        ("255.255.255.255", IpCategory::Broadcast),
+        ("192.0.0.8", IpCategory::Reserved),
+        ("240.0.0.1", IpCategory::Reserved),
+        ("255.255.255.254", IpCategory::Reserved),
+        ("100::1", IpCategory::Reserved),
+    ];
+    for (ip, category) in &cases {
+        assert_eq!(classify_ip(&return_ip(ip)), *category, "{}", ip);
+    }
+}
+
+#[test]
+fn range_boundaries_are_respected() {
+    for ip in &[
+        "172.15.255.255",
+        "172.32.0.0",
+        "100.63.255.255",
+        "100.128.0.0",
+    ] {
+        assert_eq!(classify_ip(&return_ip(ip)), IpCategory::Global, "{}", ip);
+    }
+}
+
+#[test]
+fn ipv4_mapped_ipv6_addresses_get_the_ipv4_category() {
+    let cases = [
+        ("::ffff:93.184.216.34", IpCategory::Global),
+        ("::ffff:10.0.0.1", IpCategory::Private),
+        ("::ffff:127.0.0.1", IpCategory::Loopback),
+        ("::ffff:169.254.0.1", IpCategory::LinkLocal),
+        ("::ffff:192.0.2.1", IpCategory::Documentation),
+        ("::ffff:255.255.255.255", IpCategory::Broadcast),
+    ];
+    for (ip, category) in &cases {
+        assert_eq!(classify_ip(&return_ip(ip)), *category, "{}", ip);
+    }
+}
+
+#[test]
+fn only_global_and_internal_ips_are_scannable() {
+    assert!(IpCategory::Global.is_scannable(false));
+    for category in &[
+        IpCategory::Private,
+        IpCategory::SharedAddress,
+        IpCategory::LinkLocal,
+        IpCategory::UniqueLocal,
+    ] {
+        assert!(category.is_internal());
+        assert!(!category.is_scannable(false));
+        assert!(category.is_scannable(true));
+    }
+    for category in &[
+        IpCategory::Unspecified,
+        IpCategory::Loopback,
+        IpCategory::Documentation,
+        IpCategory::Benchmarking,
+        IpCategory::Multicast,
+        IpCategory::Broadcast,
+        IpCategory::Reserved,
+    ] {
+        assert!(!category.is_scannable(true), "{}", category);
+    }
+}
@@ -0,0 +1,64 @@
+use unimap::masscan::parse_masscan_file;
+
+const RECORDS: &str = "tests/fixtures/masscan/records.json";
+const ARRAY: &str = "tests/fixtures/masscan/array.json";
+const EMPTY: &str = "tests/fixtures/masscan/empty.json";
+const INVALID: &str = "tests/fixtures/masscan/invalid.json";
+
+fn return_portids(fixture: &str, host: &str) -> Vec<String> {
+    parse_masscan_file(fixture, host)
+        .unwrap()
+        .into_iter()
+        .map(|port| port.portid)
+        .collect()
+}
+
+#[test]
+fn records_with_trailing_commas_and_the_finished_line() {
+    let ports = parse_masscan_file(RECORDS, "192.0.2.10").unwrap();
+    assert_eq!(
+        ports
+            .iter()
+            .map(|port| port.portid.as_str())
+            .collect::<Vec<&str>>(),
+        vec!["80", "443"]
+    );
+    assert_eq!(ports[1].protocol, "tcp");
+    assert_eq!(ports[1].state.state, "open");
+    assert_eq!(ports[1].state.reason, "syn-ack");
+    assert_eq!(ports[1].state.reason_ttl, "54");
+    assert!(ports[1].service.is_none());
+}
+
+#[test]
+fn json_arrays_with_commas_in_their_own_lines() {
+    assert_eq!(return_portids(ARRAY, "2001:db8::10"), vec!["80", "443"]);
+    let ports = parse_masscan_file(ARRAY, "2001:db8::10").unwrap();
+    // The TTL isn't always there
+    assert_eq!(ports[0].state.reason_ttl, "");
+}
+
+#[test]
+fn batch_outputs_are_split_by_ip() {
+    assert_eq!(return_portids(RECORDS, "192.0.2.20"), vec!["22"]);
+    assert_eq!(return_portids(ARRAY, "2001:db8::20"), vec!["22"]);
+    // Same IP written another way
+    assert_eq!(return_portids(ARRAY, "2001:0db8::0020"), vec!["22"]);
+    assert!(return_portids(RECORDS, "192.0.2.30").is_empty());
+}
+
+#[test]
+fn closed_ports_are_left_out() {
+    assert!(!return_portids(RECORDS, "192.0.2.10").contains(&"8080".to_string()));
+}
+
+#[test]
+fn empty_outputs_have_no_ports() {
+    assert!(return_portids(EMPTY, "192.0.2.10").is_empty());
+}
+
+#[test]
+fn invalid_and_missing_outputs_are_errors() {
+    assert!(parse_masscan_file(INVALID, "192.0.2.10").is_err());
+    assert!(parse_masscan_file("tests/fixtures/masscan/missing.json", "192.0.2.10").is_err());
+}
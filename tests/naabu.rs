@@ -0,0 +1,56 @@
+use unimap::naabu::parse_naabu_file;
+
+const NUMBERS: &str = "tests/fixtures/naabu/numbers.jsonl";
+const OBJECTS: &str = "tests/fixtures/naabu/objects.jsonl";
+
+fn return_portids(fixture: &str, host: &str) -> Vec<String> {
+    parse_naabu_file(fixture, host)
+        .unwrap()
+        .into_iter()
+        .map(|port| port.portid)
+        .collect()
+}
+
+#[test]
+fn ports_written_as_numbers() {
+    let ports = parse_naabu_file(NUMBERS, "192.0.2.10").unwrap();
+    assert_eq!(
+        ports
+            .iter()
+            .map(|port| port.portid.as_str())
+            .collect::<Vec<&str>>(),
+        vec!["80", "443"]
+    );
+    for port in &ports {
+        assert_eq!(port.protocol, "tcp");
+        assert_eq!(port.state.state, "open");
+        assert!(port.service.is_none());
+    }
+}
+
+#[test]
+fn ports_written_as_objects() {
+    let ports = parse_naabu_file(OBJECTS, "192.0.2.10").unwrap();
+    assert_eq!(
+        ports
+            .iter()
+            .map(|port| port.portid.as_str())
+            .collect::<Vec<&str>>(),
+        vec!["443", "8443"]
+    );
+    // Old versions don't write the protocol
+    assert!(ports.iter().all(|port| port.protocol == "tcp"));
+}
+
+#[test]
+fn batch_outputs_are_split_by_ip() {
+    assert_eq!(return_portids(NUMBERS, "192.0.2.20"), vec!["22"]);
+    // Records without the ip field are matched by the host
+    assert_eq!(return_portids(OBJECTS, "192.0.2.20"), vec!["22"]);
+    assert!(return_portids(NUMBERS, "192.0.2.30").is_empty());
+}
+
+#[test]
+fn missing_outputs_are_errors() {
+    assert!(parse_naabu_file("tests/fixtures/naabu/missing.jsonl", "192.0.2.10").is_err());
+}
@@ -0,0 +1,192 @@
+use {
+    std::net::SocketAddr,
+    unimap::nameservers::{Nameserver, NameserverProtocol},
+};
+
+fn return_nameserver(nameserver: &str) -> Nameserver {
+    nameserver.parse().unwrap()
+}
+
+fn return_socket_addr(socket_addr: &str) -> SocketAddr {
+    socket_addr.parse().unwrap()
+}
+
+#[test]
+fn plain_nameservers() {
+    let nameserver = return_nameserver("1.1.1.1");
+    assert_eq!(nameserver.socket_addr, return_socket_addr("1.1.1.1:53"));
+    assert_eq!(nameserver.protocol, NameserverProtocol::Plain);
+    assert!(nameserver.tls_dns_name.is_none());
+    assert!(nameserver.is_plain());
+
+    assert_eq!(
+        return_nameserver("1.1.1.1:5353").socket_addr,
+        return_socket_addr("1.1.1.1:5353")
+    );
+    assert_eq!(
+        return_nameserver("2606:4700:4700::1111").socket_addr,
+        return_socket_addr("[2606:4700:4700::1111]:53")
+    );
+    assert_eq!(
+        return_nameserver("[2606:4700:4700::1111]:5353").socket_addr,
+        return_socket_addr("[2606:4700:4700::1111]:5353")
+    );
+    assert_eq!(return_nameserver("1.1.1.1").to_string(), "1.1.1.1:53");
+}
+
+#[test]
+fn invalid_plain_nameservers_are_rejected() {
+    // Plain nameservers aren't resolved, only IPs are accepted
+    for nameserver in &[
+        "",
+        "dns.google",
+        "1.1.1",
+        "1.1.1.1:",
+        "1.1.1.1:70000",
+        "1.1.1.1#cloudflare-dns.com",
+        "2606:4700:4700::1111:53:53:53:53",
+    ] {
+        assert!(
+            nameserver.parse::<Nameserver>().is_err(),
+            "{} was accepted",
+            nameserver
+        );
+    }
+}
+
+#[test]
+fn unknown_protocols_are_rejected() {
+    for nameserver in &[
+        "udp://1.1.1.1",
+        "quic://1.1.1.1#cloudflare-dns.com",
+        "://1.1.1.1",
+    ] {
+        assert!(
+            nameserver.parse::<Nameserver>().is_err(),
+            "{} was accepted",
+            nameserver
+        );
+    }
+}
+
+#[cfg(feature = "dns-over-tls")]
+#[test]
+fn tls_nameservers() {
+    let nameserver = return_nameserver("tls://1.1.1.1#cloudflare-dns.com");
+    assert_eq!(nameserver.socket_addr, return_socket_addr("1.1.1.1:853"));
+    assert_eq!(nameserver.protocol, NameserverProtocol::Tls);
+    assert_eq!(
+        nameserver.tls_dns_name.as_deref(),
+        Some("cloudflare-dns.com")
+    );
+    assert!(!nameserver.is_plain());
+    assert_eq!(
+        nameserver.to_string(),
+        "tls://1.1.1.1:853#cloudflare-dns.com"
+    );
+
+    assert_eq!(
+        return_nameserver("tls://1.1.1.1:8853#cloudflare-dns.com").socket_addr,
+        return_socket_addr("1.1.1.1:8853")
+    );
+    assert_eq!(
+        return_nameserver("tls://[2606:4700:4700::1111]:8853#cloudflare-dns.com").socket_addr,
+        return_socket_addr("[2606:4700:4700::1111]:8853")
+    );
+    // Hostnames are the TLS name unless another one is given
+    let nameserver = return_nameserver("tls://localhost");
+    assert_eq!(nameserver.socket_addr.port(), 853);
+    assert_eq!(nameserver.tls_dns_name.as_deref(), Some("localhost"));
+    assert_eq!(
+        return_nameserver("tls://localhost:8853#dns.example.com")
+            .tls_dns_name
+            .as_deref(),
+        Some("dns.example.com")
+    );
+}
+
+#[cfg(feature = "dns-over-tls")]
+#[test]
+fn invalid_tls_nameservers_are_rejected() {
+    for nameserver in &[
+        // IPs don't tell the TLS name
+        "tls://1.1.1.1",
+        "tls://1.1.1.1:853",
+        "tls://1.1.1.1:port#cloudflare-dns.com",
+        "tls://localhost:70000",
+        "tls://unimap.invalid",
+    ] {
+        assert!(
+            nameserver.parse::<Nameserver>().is_err(),
+            "{} was accepted",
+            nameserver
+        );
+    }
+}
+
+#[cfg(feature = "dns-over-https")]
+#[test]
+fn https_nameservers() {
+    let nameserver = return_nameserver("https://1.1.1.1/dns-query#cloudflare-dns.com");
+    assert_eq!(nameserver.socket_addr, return_socket_addr("1.1.1.1:443"));
+    assert_eq!(nameserver.protocol, NameserverProtocol::Https);
+    assert_eq!(
+        nameserver.tls_dns_name.as_deref(),
+        Some("cloudflare-dns.com")
+    );
+    assert_eq!(
+        nameserver.to_string(),
+        "https://1.1.1.1:443/dns-query#cloudflare-dns.com"
+    );
+
+    // The path is optional
+    assert_eq!(
+        return_nameserver("https://1.1.1.1#cloudflare-dns.com"),
+        nameserver
+    );
+    assert_eq!(
+        return_nameserver("https://1.1.1.1/#cloudflare-dns.com"),
+        nameserver
+    );
+    assert_eq!(
+        return_nameserver("https://1.1.1.1:8443/dns-query#cloudflare-dns.com").socket_addr,
+        return_socket_addr("1.1.1.1:8443")
+    );
+    let nameserver = return_nameserver("https://localhost/dns-query");
+    assert_eq!(nameserver.socket_addr.port(), 443);
+    assert_eq!(nameserver.tls_dns_name.as_deref(), Some("localhost"));
+}
+
+#[cfg(feature = "dns-over-https")]
+#[test]
+fn invalid_https_nameservers_are_rejected() {
+    for nameserver in &[
+        "https://1.1.1.1/dns-query",
+        "https://1.1.1.1/resolve#cloudflare-dns.com",
+        "https://1.1.1.1/dns-query/extra#cloudflare-dns.com",
+        "https://localhost:70000/dns-query",
+        "https://unimap.invalid/dns-query",
+    ] {
+        assert!(
+            nameserver.parse::<Nameserver>().is_err(),
+            "{} was accepted",
+            nameserver
+        );
+    }
+}
+
+#[cfg(not(feature = "dns-over-tls"))]
+#[test]
+fn tls_nameservers_need_the_feature() {
+    assert!("tls://1.1.1.1#cloudflare-dns.com"
+        .parse::<Nameserver>()
+        .is_err());
+}
+
+#[cfg(not(feature = "dns-over-https"))]
+#[test]
+fn https_nameservers_need_the_feature() {
+    assert!("https://1.1.1.1/dns-query#cloudflare-dns.com"
+        .parse::<Nameserver>()
+        .is_err());
+}
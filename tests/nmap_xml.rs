@@ -0,0 +1,299 @@
+use unimap::{
+    nmap::{self, Host, Nmaprun, Port},
+    scanners::{ScanData, ScannerBackend},
+};
+
+const FULL: &str = "tests/fixtures/nmap/full.xml";
+const BATCH: &str = "tests/fixtures/nmap/batch.xml";
+const BATCH_ONE_HOST: &str = "tests/fixtures/nmap/batch_one_host.xml";
+
+fn return_nmaprun(fixture: &str) -> Nmaprun {
+    nmap::parse_nmap_file(fixture, "45.33.32.156").unwrap()
+}
+
+fn return_host() -> Host {
+    return_nmaprun(FULL).host.remove(0)
+}
+
+fn return_port(portid: &str) -> Port {
+    return_host()
+        .ports
+        .unwrap()
+        .port
+        .into_iter()
+        .find(|port| port.portid == portid)
+        .unwrap()
+}
+
+#[test]
+fn nmaprun_attributes() {
+    let nmaprun = return_nmaprun(FULL);
+    assert_eq!(nmaprun.scanner, "nmap");
+    assert_eq!(nmaprun.version, "7.94");
+    assert_eq!(nmaprun.xmloutputversion, "1.05");
+    assert_eq!(nmaprun.start, "1696241701");
+    assert!(nmaprun.profile_name.is_none());
+    assert_eq!(nmaprun.host.len(), 1);
+}
+
+#[test]
+fn scaninfo_verbose_and_debugging() {
+    let nmaprun = return_nmaprun(FULL);
+    assert_eq!(nmaprun.scaninfo.len(), 2);
+    assert_eq!(nmaprun.scaninfo[0].type_field, "syn");
+    assert_eq!(nmaprun.scaninfo[1].protocol, "udp");
+    assert_eq!(nmaprun.scaninfo[1].services, "53");
+    assert_eq!(nmaprun.verbose.unwrap().level.as_deref(), Some("0"));
+    assert_eq!(nmaprun.debugging.unwrap().level.as_deref(), Some("2"));
+}
+
+#[test]
+fn target_tasks_hosthint_and_output() {
+    let nmaprun = return_nmaprun(FULL);
+    assert_eq!(nmaprun.target[0].specification, "nonexistent.invalid");
+    assert_eq!(nmaprun.target[0].reason.as_deref(), Some("invalid"));
+    assert_eq!(nmaprun.taskbegin[0].task, "SYN Stealth Scan");
+    assert_eq!(nmaprun.taskprogress[0].percent, "33.33");
+    assert_eq!(
+        nmaprun.taskend[0].extrainfo.as_deref(),
+        Some("3 total ports")
+    );
+    assert_eq!(
+        nmaprun.hosthint[0].address[0].addr.as_deref(),
+        Some("45.33.32.156")
+    );
+    assert_eq!(nmaprun.output[0].type_field.as_deref(), Some("interactive"));
+    assert!(nmaprun.output[0].value.starts_with("Increasing send delay"));
+}
+
+#[test]
+fn prescript_and_postscript() {
+    let nmaprun = return_nmaprun(FULL);
+    assert_eq!(
+        nmaprun.prescript.unwrap().script[0].id,
+        "broadcast-dns-service-discovery"
+    );
+    let postscript = nmaprun.postscript.unwrap();
+    assert_eq!(postscript.script[0].id, "ssh-hostkey-summary");
+    assert_eq!(postscript.script[0].output, "\n  1 hosts with 2 keys");
+}
+
+#[test]
+fn runstats() {
+    let runstats = return_nmaprun(FULL).runstats.unwrap();
+    assert_eq!(runstats.finished.elapsed, "89.25");
+    assert_eq!(runstats.finished.exit.as_deref(), Some("success"));
+    assert_eq!(runstats.hosts.up, "1");
+    assert_eq!(runstats.hosts.total, "1");
+}
+
+#[test]
+fn host_status_and_times() {
+    let host = return_host();
+    assert_eq!(host.starttime, "1696241702");
+    assert_eq!(host.status.reason, "echo-reply");
+    let times = host.times.unwrap();
+    assert_eq!(times.srtt, "166123");
+    assert_eq!(times.rttvar, "1517");
+    assert_eq!(times.to, "172191");
+}
+
+#[test]
+fn multiple_addresses() {
+    let host = return_host();
+    assert_eq!(host.address.len(), 2);
+    assert_eq!(host.address[0].addrtype.as_deref(), Some("ipv4"));
+    assert_eq!(host.address[1].addrtype.as_deref(), Some("mac"));
+    assert_eq!(host.address[1].vendor.as_deref(), Some("Cisco Systems"));
+}
+
+#[test]
+fn multiple_hostnames() {
+    let hostnames = return_host().hostnames.hostname;
+    assert_eq!(hostnames.len(), 2);
+    assert_eq!(hostnames[0].type_field, "user");
+    assert_eq!(hostnames[1].type_field, "PTR");
+    assert_eq!(hostnames[1].name, "scanme.nmap.org");
+}
+
+#[test]
+fn extraports() {
+    let extraports = return_host().ports.unwrap().extraports;
+    assert_eq!(extraports.len(), 1);
+    assert_eq!(extraports[0].state, "closed");
+    assert_eq!(extraports[0].count, "995");
+    assert_eq!(extraports[0].extrareasons.len(), 2);
+    assert_eq!(extraports[0].extrareasons[1].reason, "port-unreach");
+    assert_eq!(extraports[0].extrareasons[1].proto.as_deref(), Some("udp"));
+}
+
+#[test]
+fn port_state_owner_and_service() {
+    let ssh = return_port("22");
+    assert_eq!(ssh.owner.unwrap().name, "root");
+    let service = ssh.service.unwrap();
+    assert_eq!(service.product.as_deref(), Some("OpenSSH"));
+    assert_eq!(service.ostype.as_deref(), Some("Linux"));
+    assert_eq!(service.cpe.len(), 2);
+    assert_eq!(service.cpe[0], "cpe:/a:openbsd:openssh:6.6.1p1");
+
+    let https = return_port("443").service.unwrap();
+    assert_eq!(https.tunnel.as_deref(), Some("ssl"));
+    assert!(https.servicefp.unwrap().starts_with("SF-Port443-TCP"));
+
+    let domain = return_port("53");
+    assert_eq!(domain.protocol, "udp");
+    assert_eq!(domain.state.state, "open|filtered");
+    assert_eq!(domain.state.reason_ip.as_deref(), Some("45.33.32.156"));
+}
+
+#[test]
+fn port_scripts() {
+    let http = return_port("80");
+    assert_eq!(http.script.len(), 2);
+    assert_eq!(http.script[0].id, "http-title");
+    assert_eq!(http.script[0].output, "Go ahead and ScanMe!");
+    assert_eq!(http.script[0].elem[0].key.as_deref(), Some("title"));
+    assert_eq!(http.script[0].elem[0].value, "Go ahead and ScanMe!");
+    assert!(http.script[1].elem[0].key.is_none());
+
+    let ssh = return_port("22");
+    assert_eq!(ssh.script[0].table.len(), 2);
+    assert_eq!(ssh.script[0].table[1].elem[0].value, "ssh-rsa");
+}
+
+#[test]
+fn nested_script_tables() {
+    let ssl_cert = &return_port("443").script[0];
+    assert_eq!(ssl_cert.id, "ssl-cert");
+    assert!(ssl_cert.output.contains("\nNot valid before"));
+    let keys: Vec<&str> = ssl_cert
+        .table
+        .iter()
+        .filter_map(|table| table.key.as_deref())
+        .collect();
+    assert_eq!(
+        keys,
+        vec!["subject", "issuer", "pubkey", "extensions", "validity"]
+    );
+    assert_eq!(ssl_cert.elem.len(), 2);
+    assert_eq!(ssl_cert.elem[0].key.as_deref(), Some("sig_algo"));
+    assert_eq!(ssl_cert.table[1].elem[1].value, "Let's Encrypt");
+    let extension = &ssl_cert.table[3].table[0];
+    assert!(extension.key.is_none());
+    assert_eq!(extension.elem[1].value, "DNS:scanme.nmap.org");
+}
+
+#[test]
+fn hostscript() {
+    let scripts = return_host().hostscript.unwrap().script;
+    assert_eq!(scripts.len(), 2);
+    assert_eq!(scripts[0].id, "clock-skew");
+    assert_eq!(scripts[0].elem[2].key.as_deref(), Some("count"));
+    assert_eq!(scripts[0].elem[2].value, "3");
+}
+
+#[test]
+fn os() {
+    let os = return_host().os.unwrap();
+    assert_eq!(os.portused.len(), 3);
+    assert_eq!(os.portused[2].proto, "udp");
+    assert_eq!(os.osmatch.len(), 2);
+    assert_eq!(os.osmatch[0].name, "Linux 4.15 - 5.8");
+    assert_eq!(os.osmatch[0].accuracy, "96");
+    assert_eq!(os.osmatch[0].osclass.len(), 2);
+    assert_eq!(os.osmatch[0].osclass[1].osgen.as_deref(), Some("5.X"));
+    assert_eq!(
+        os.osmatch[0].osclass[0].cpe,
+        vec!["cpe:/o:linux:linux_kernel:4"]
+    );
+    assert!(os.osfingerprint[0].fingerprint.starts_with("OS:SCAN"));
+}
+
+#[test]
+fn uptime_and_distance() {
+    let host = return_host();
+    let uptime = host.uptime.unwrap();
+    assert_eq!(uptime.seconds, "1733594");
+    assert_eq!(uptime.lastboot.as_deref(), Some("Tue Sep 12 08:36:16 2023"));
+    assert_eq!(host.distance.unwrap().value, "11");
+}
+
+#[test]
+fn sequences() {
+    let host = return_host();
+    let tcpsequence = host.tcpsequence.unwrap();
+    assert_eq!(tcpsequence.index, "262");
+    assert_eq!(tcpsequence.difficulty, "Good luck!");
+    assert_eq!(host.ipidsequence.unwrap().class, "All zeros");
+    assert_eq!(host.tcptssequence.unwrap().class, "1000HZ");
+}
+
+#[test]
+fn trace() {
+    let trace = return_host().trace.unwrap();
+    assert_eq!(trace.proto.as_deref(), Some("tcp"));
+    assert_eq!(trace.port.as_deref(), Some("22"));
+    assert_eq!(trace.hop.len(), 4);
+    assert_eq!(trace.hop[1].host.as_deref(), Some("gw.isp.example"));
+    // Hops that didn't answer only have the TTL
+    assert_eq!(trace.hop[2].ttl, "3");
+    assert!(trace.hop[2].ipaddr.is_none());
+    assert_eq!(trace.hop[3].rtt.as_deref(), Some("166.86"));
+}
+
+#[test]
+fn hosts_of_a_batch_run() {
+    let nmaprun = return_nmaprun(BATCH);
+    assert_eq!(nmaprun.host.len(), 2);
+    assert_eq!(nmaprun.hosthint.len(), 2);
+    assert_eq!(nmaprun.taskend.len(), 2);
+
+    let first = nmaprun.return_host("192.0.2.10").unwrap();
+    assert_eq!(first.ports.as_ref().unwrap().port[0].portid, "80");
+    let second = nmaprun.return_host("192.0.2.20").unwrap();
+    let ports = &second.ports.as_ref().unwrap().port;
+    assert_eq!(ports.len(), 2);
+    assert_eq!(
+        ports[0].service.as_ref().unwrap().hostname.as_deref(),
+        Some("mail.example.com")
+    );
+    assert!(nmaprun.return_host("192.0.2.30").is_none());
+    assert_eq!(nmaprun.runstats.unwrap().hosts.down, "1");
+}
+
+#[test]
+fn ips_without_a_host_of_a_batch_run_are_empty() {
+    // Only the second IP had open ports, --open leaves out the first one
+    let nmaprun = return_nmaprun(BATCH_ONE_HOST);
+    assert_eq!(nmaprun.host.len(), 1);
+    assert!(nmaprun.return_host("192.0.2.10").is_none());
+    assert!(nmaprun.return_host("192.0.2.20").is_some());
+
+    let scanner = ScannerBackend::Nmap.port_scanner();
+    assert_eq!(
+        scanner.parse(BATCH_ONE_HOST, "192.0.2.10").unwrap(),
+        ScanData::default()
+    );
+    let scan_data = scanner.parse(BATCH_ONE_HOST, "192.0.2.20").unwrap();
+    assert_eq!(scan_data.ports.len(), 1);
+    assert_eq!(scan_data.ports[0].portid, "443");
+    assert_eq!(scan_data.host_scripts[0].id, "clock-skew");
+}
+
+#[test]
+fn json_output_keeps_everything() {
+    let json = serde_json::to_value(return_nmaprun(FULL)).unwrap();
+    let host = &json["host"][0];
+    assert_eq!(host["os"]["osmatch"][0]["name"], "Linux 4.15 - 5.8");
+    assert_eq!(host["trace"]["hop"][3]["ipaddr"], "45.33.32.156");
+    assert_eq!(
+        host["ports"]["port"][0]["script"][0]["table"][0]["elem"][0]["value"],
+        "ssh-dss"
+    );
+    assert_eq!(
+        host["ports"]["port"][3]["state"]["reason_ip"],
+        "45.33.32.156"
+    );
+    assert_eq!(json["runstats"]["hosts"]["up"], "1");
+}
@@ -0,0 +1,68 @@
+use unimap::{
+    output::return_table,
+    structs::{ResolvData, ResolveFailure, ScanReport},
+};
+
+fn return_report(hosts: Vec<(&str, ResolvData)>) -> ScanReport {
+    ScanReport {
+        hosts: hosts
+            .into_iter()
+            .map(|(host, resolv_data)| (host.to_string(), resolv_data))
+            .collect(),
+        ..Default::default()
+    }
+}
+
+fn return_failed(failure: ResolveFailure) -> ResolvData {
+    ResolvData {
+        failure: Some(failure),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn failed_hosts_are_in_the_table() {
+    let report = return_report(vec![
+        ("gone.example.com", return_failed(ResolveFailure::Nxdomain)),
+        (
+            "broken.example.com",
+            return_failed(ResolveFailure::Servfail),
+        ),
+        ("slow.example.com", return_failed(ResolveFailure::Timeout)),
+        ("mx.example.com", return_failed(ResolveFailure::NoAddress)),
+        (
+            "www.example.com",
+            ResolvData {
+                ips: vec!["93.184.216.34".to_string()],
+                ..Default::default()
+            },
+        ),
+    ]);
+    let table = return_table(&report);
+    // The titles aren't a row
+    assert_eq!(table.len(), 5);
+
+    let rendered = table.to_string();
+    for (host, ips) in &[
+        ("gone.example.com", "NULL (NXDOMAIN)"),
+        ("broken.example.com", "NULL (SERVFAIL)"),
+        ("slow.example.com", "NULL (timeout)"),
+        ("mx.example.com", "NULL (no A record)"),
+        ("www.example.com", "93.184.216.34"),
+    ] {
+        assert!(
+            rendered
+                .lines()
+                .any(|line| line.contains(host) && line.contains(ips)),
+            "{} isn't shown as {}",
+            host,
+            ips
+        );
+    }
+}
+
+#[test]
+fn hosts_without_any_data_are_left_out() {
+    let report = return_report(vec![("empty.example.com", ResolvData::default())]);
+    assert!(return_table(&report).is_empty());
+}
@@ -0,0 +1,139 @@
+use {
+    std::{
+        collections::HashSet,
+        net::{Ipv4Addr, SocketAddr, UdpSocket},
+        thread,
+        time::Duration,
+    },
+    trust_dns_resolver::proto::{
+        op::{Message, MessageType, ResponseCode},
+        rr::{RData, Record},
+    },
+    unimap::{nameservers::Nameserver, resolver_engine, structs::ScanConfig},
+};
+
+const DOMAIN: &str = "known.test.";
+
+#[derive(Clone, Copy)]
+enum Behavior {
+    Healthy,
+    HijacksNxdomain,
+    Slow,
+    Silent,
+}
+
+/// Starts a UDP DNS server on a random local port that answers `DOMAIN` with
+/// an A record and everything else according to `behavior`.
+fn start_stub_server(behavior: Behavior) -> SocketAddr {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let address = socket.local_addr().unwrap();
+    thread::spawn(move || {
+        let mut buffer = [0; 512];
+        while let Ok((len, peer)) = socket.recv_from(&mut buffer) {
+            let request = match Message::from_vec(&buffer[..len]) {
+                Ok(request) => request,
+                Err(_) => continue,
+            };
+            let query = request.queries()[0].clone();
+            let mut response = Message::new();
+            response
+                .set_id(request.id())
+                .set_message_type(MessageType::Response)
+                .set_op_code(request.op_code())
+                .set_recursion_desired(true)
+                .set_recursion_available(true)
+                .add_query(query.clone());
+            match behavior {
+                Behavior::Silent => continue,
+                Behavior::Slow => thread::sleep(Duration::from_millis(300)),
+                _ => (),
+            }
+            if query.name().to_string() == DOMAIN || matches!(behavior, Behavior::HijacksNxdomain) {
+                response.add_answer(Record::from_rdata(
+                    query.name().clone(),
+                    60,
+                    RData::A(Ipv4Addr::new(192, 0, 2, 1)),
+                ));
+            } else {
+                response.set_response_code(ResponseCode::NXDomain);
+            }
+            socket.send_to(&response.to_vec().unwrap(), peer).unwrap();
+        }
+    });
+    address
+}
+
+fn return_config() -> ScanConfig {
+    ScanConfig::builder()
+        .health_check_domain(DOMAIN)
+        .max_resolver_latency(Duration::from_millis(200))
+        .build()
+}
+
+#[test]
+fn healthy_resolver_is_kept() {
+    let nameserver = start_stub_server(Behavior::Healthy);
+    let checks = resolver_engine::check_resolvers(
+        &return_config(),
+        &HashSet::from([Nameserver::from(nameserver)]),
+    );
+
+    assert_eq!(checks.len(), 1);
+    assert!(checks[0].is_healthy(), "{:?}", checks[0].problem);
+    assert_eq!(checks[0].resolver, nameserver.to_string());
+    assert!(checks[0].latency_ms.is_some());
+}
+
+#[test]
+fn nxdomain_hijacking_resolver_is_dropped() {
+    let nameserver = start_stub_server(Behavior::HijacksNxdomain);
+    let checks = resolver_engine::check_resolvers(
+        &return_config(),
+        &HashSet::from([Nameserver::from(nameserver)]),
+    );
+
+    assert!(!checks[0].is_healthy());
+    assert!(checks[0].problem.as_ref().unwrap().contains("NXDOMAIN"));
+}
+
+#[test]
+fn slow_resolver_is_dropped() {
+    let nameserver = start_stub_server(Behavior::Slow);
+    let checks = resolver_engine::check_resolvers(
+        &return_config(),
+        &HashSet::from([Nameserver::from(nameserver)]),
+    );
+
+    assert!(!checks[0].is_healthy());
+    assert!(checks[0].latency_ms.unwrap() >= 200);
+}
+
+#[test]
+fn unresponsive_resolver_is_dropped() {
+    let nameserver = start_stub_server(Behavior::Silent);
+    let checks = resolver_engine::check_resolvers(
+        &return_config(),
+        &HashSet::from([Nameserver::from(nameserver)]),
+    );
+
+    assert!(!checks[0].is_healthy());
+}
+
+#[test]
+fn only_healthy_resolvers_pass() {
+    let healthy = start_stub_server(Behavior::Healthy);
+    let nameservers = HashSet::from([
+        Nameserver::from(healthy),
+        Nameserver::from(start_stub_server(Behavior::HijacksNxdomain)),
+        Nameserver::from(start_stub_server(Behavior::Silent)),
+    ]);
+    let checks = resolver_engine::check_resolvers(&return_config(), &nameservers);
+
+    let passed: Vec<&str> = checks
+        .iter()
+        .filter(|check| check.is_healthy())
+        .map(|check| check.resolver.as_str())
+        .collect();
+    assert_eq!(checks.len(), 3);
+    assert_eq!(passed, vec![healthy.to_string()]);
+}
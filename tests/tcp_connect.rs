@@ -0,0 +1,124 @@
+use {
+    std::{
+        net::{TcpListener, TcpStream},
+        path::PathBuf,
+        time::Duration,
+    },
+    unimap::{scanners::ScannerBackend, structs::ScanConfig, tcp_connect::return_port_list},
+};
+
+/// Returns a port of 127.0.0.1 where nothing is listening.
+fn return_closed_port() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap().port()
+}
+
+fn return_output_file(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("unimap-{}-{}.json", name, std::process::id()))
+}
+
+fn return_config(ports: &[u16]) -> ScanConfig {
+    ScanConfig::builder()
+        .scanner(ScannerBackend::Native)
+        .ports(
+            &ports
+                .iter()
+                .map(u16::to_string)
+                .collect::<Vec<String>>()
+                .join(", "),
+        )
+        .connect_timeout(Duration::from_millis(500))
+        .build()
+}
+
+#[test]
+fn open_ports_are_found() {
+    let first = TcpListener::bind("127.0.0.1:0").unwrap();
+    let second = TcpListener::bind("127.0.0.1:0").unwrap();
+    let open_ports = [
+        first.local_addr().unwrap().port(),
+        second.local_addr().unwrap().port(),
+    ];
+    let closed_port = return_closed_port();
+    let config = return_config(&[open_ports[0], open_ports[1], closed_port]);
+    let filename = return_output_file("open");
+
+    let ports = config
+        .scanner
+        .port_scanner()
+        .scan(filename.to_str().unwrap(), "127.0.0.1", &config)
+        .unwrap()
+        .ports;
+
+    let mut expected: Vec<String> = open_ports.iter().map(u16::to_string).collect();
+    expected.sort_by_key(|port| port.parse::<u16>().unwrap());
+    assert_eq!(
+        ports
+            .iter()
+            .map(|port| port.portid.clone())
+            .collect::<Vec<String>>(),
+        expected
+    );
+    for port in &ports {
+        assert_eq!(port.protocol, "tcp");
+        assert_eq!(port.state.state, "open");
+        assert!(port.service.is_none());
+    }
+    std::fs::remove_file(filename).unwrap();
+}
+
+#[test]
+fn closed_ports_are_not_reported() {
+    let config = return_config(&[return_closed_port(), return_closed_port()]);
+    let filename = return_output_file("closed");
+
+    let ports = config
+        .scanner
+        .port_scanner()
+        .scan(filename.to_str().unwrap(), "127.0.0.1", &config)
+        .unwrap()
+        .ports;
+
+    assert!(ports.is_empty());
+    std::fs::remove_file(filename).unwrap();
+}
+
+#[test]
+fn saved_results_are_parsed_back() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let config = return_config(&[port]);
+    let filename = return_output_file("saved");
+    let scanner = config.scanner.port_scanner();
+
+    let ports = scanner
+        .scan(filename.to_str().unwrap(), "127.0.0.1", &config)
+        .unwrap()
+        .ports;
+    // The listener must not be needed anymore
+    drop(listener);
+    assert!(TcpStream::connect(("127.0.0.1", port)).is_err());
+
+    assert_eq!(
+        scanner
+            .parse(filename.to_str().unwrap(), "127.0.0.1")
+            .unwrap()
+            .ports,
+        ports
+    );
+    std::fs::remove_file(filename).unwrap();
+}
+
+#[test]
+fn port_lists_are_expanded() {
+    assert_eq!(
+        return_port_list("22, 80,T:443, 1000-1003").unwrap(),
+        vec![22, 80, 443, 1000, 1001, 1002, 1003]
+    );
+    assert_eq!(return_port_list("65534-").unwrap(), vec![65534, 65535]);
+    assert_eq!(return_port_list("").unwrap().len(), 100);
+    assert!(return_port_list("U:53").is_err());
+    assert!(return_port_list("0").is_err());
+    assert!(return_port_list("90-80").is_err());
+    assert!(return_port_list("http").is_err());
+}